@@ -1,7 +1,14 @@
 pub mod args;
 pub mod commands;
 pub mod env;
+pub mod expand;
 pub mod input;
+pub mod jobs;
 pub mod operators;
+pub mod options;
 pub mod pipline;
 pub mod rustyline;
+pub mod script;
+pub mod signals;
+pub mod times;
+pub mod vars;
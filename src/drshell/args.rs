@@ -1,44 +1,109 @@
 use super::commands;
-use super::operators;
+use super::script;
+use super::vars;
+use std::io::{self, IsTerminal};
+use std::path::Path;
 
 pub enum ArgsParseSuccess {
-    SubCmdExeOver,
-    NoArgs,
+    SubCmdExeOver(i32),
+    ReadCmds(ShellOpts),
+}
+
+/* how to read the commands when there is no -c or script file */
+pub struct ShellOpts {
+    pub interactive: bool,
+    pub login: bool,
+    pub norc: bool,
+    pub noprofile: bool,
+    pub rcfile: Option<String>,
 }
 
 pub enum ArgsParseFail {}
 
+fn run_string(mut args: Vec<String>) -> i32 {
+    /* drshell -c STRING [NAME [ARGS]] */
+    if args.is_empty() {
+        eprintln!("drshell: -c: option requires an argument");
+        return 2;
+    }
+    let line = args.remove(0);
+    if !args.is_empty() {
+        vars::set_script_name(args.remove(0));
+    }
+    vars::set_positional_params(args);
+    script::print_verbose(&line);
+    script::run_line(&line)
+}
+
+fn run_script(mut args: Vec<String>) -> i32 {
+    /* drshell FILE ARGS */
+    let file = args.remove(0);
+    vars::set_script_name(file.clone());
+    vars::set_positional_params(args);
+    match script::run_file(Path::new(&file)) {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("drshell: {}: {}", file, commands::error_reason(&err));
+            127
+        }
+    }
+}
+
 pub fn parse_args(args: impl Iterator<Item = String>) -> Result<ArgsParseSuccess, ArgsParseFail> {
-    let args: Vec<String> = args.into_iter().collect();
-    if args.len() <= 1 {
-        return Ok(ArgsParseSuccess::NoArgs);
+    let mut args: Vec<String> = args.into_iter().collect();
+    let mut opts = ShellOpts {
+        interactive: false,
+        login: false,
+        norc: false,
+        noprofile: false,
+        rcfile: None,
+    };
+    if let Some(arg_0) = args.first() {
+        /* the login shells are started as -drshell */
+        opts.login = arg_0.starts_with('-');
+        vars::set_script_name(arg_0.to_string());
     }
-    match args.get(1).unwrap().as_str() {
-        "-c" => {
-            let mut cmd = vec![];
-            for (i, arg) in args.iter().enumerate() {
-                if i > 1 {
-                    cmd.push(arg.to_string());
-                }
-            }
-            match commands::parse_cmd(cmd) {
-                Ok(cmd) => commands::eval(cmd),
-                Err(reason) => commands::parse_cmd_fail_process(reason),
+
+    let mut force_interactive = false;
+    let mut read_stdin = false;
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "-c" => {
+                script::run_startup_files(&opts);
+                let status = run_string(args.split_off(i + 1));
+                return Ok(ArgsParseSuccess::SubCmdExeOver(status));
             }
-        }
-        "-o" => {
-            let mut opt = vec![];
-            for (i, arg) in args.iter().enumerate() {
-                if i > 1 {
-                    opt.push(arg.to_string());
-                }
+            "-i" => force_interactive = true,
+            "-s" => read_stdin = true,
+            "-l" | "--login" => opts.login = true,
+            "--norc" => opts.norc = true,
+            "--noprofile" => opts.noprofile = true,
+            "--rcfile" => {
+                i += 1;
+                let Some(file) = args.get(i) else {
+                    eprintln!("drshell: --rcfile: option requires an argument");
+                    return Ok(ArgsParseSuccess::SubCmdExeOver(2));
+                };
+                opts.rcfile = Some(file.to_string());
             }
-            match operators::parse_opt(opt) {
-                Ok(opt) => operators::operator_action(opt),
-                Err(reason) => operators::parse_opt_fail_process(reason),
+            "--" => {
+                i += 1;
+                break;
             }
+            _ => break,
         }
-        &_ => {}
+        i += 1;
+    }
+
+    let args = args.split_off(i.min(args.len()));
+    if !read_stdin && !args.is_empty() {
+        script::run_startup_files(&opts);
+        return Ok(ArgsParseSuccess::SubCmdExeOver(run_script(args)));
     }
-    Ok(ArgsParseSuccess::SubCmdExeOver)
+
+    /* drshell [-s] [ARGS] reads the commands from stdin */
+    vars::set_positional_params(args);
+    opts.interactive = force_interactive || io::stdin().is_terminal();
+    Ok(ArgsParseSuccess::ReadCmds(opts))
 }
@@ -1,16 +1,61 @@
+mod alias;
 mod cd;
+mod coproc;
 mod echo;
+mod exec;
 mod exit;
+mod export;
 mod help;
 mod history;
+mod jobs;
+mod kill;
 mod lsbuiltin;
+mod parallel;
 mod pwd;
+mod read;
+mod set;
+mod source;
+mod times;
+mod trap;
 mod r#type;
+mod ulimit;
+mod umask;
 
-use super::env as drshell_env;
+use super::env::{self as drshell_env, ExeFindFail};
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::process::CommandExt;
 use std::process::Command;
 
-pub const BUILTIN_CMDS: [&str; 7] = ["echo", "exit", "type", "pwd", "cd", "lsbuiltin", "history"];
+pub const BUILTIN_CMDS: [&str; 26] = [
+    "echo",
+    "exit",
+    "type",
+    "pwd",
+    "cd",
+    "lsbuiltin",
+    "history",
+    "alias",
+    "unalias",
+    "source",
+    ".",
+    "export",
+    "set",
+    "trap",
+    "jobs",
+    "fg",
+    "bg",
+    "wait",
+    "kill",
+    "disown",
+    "exec",
+    "times",
+    "ulimit",
+    "umask",
+    "parallel",
+    "read",
+];
 
 #[allow(dead_code)]
 const BUILTIN_ARGS: [&str; 1] = ["-h"];
@@ -18,16 +63,52 @@ const BUILTIN_ARGS: [&str; 1] = ["-h"];
 #[derive(Debug)]
 pub enum Cmd {
     Echo(Vec<String>),
-    Exit(i32),
+    /* without a number, the status of the last command */
+    Exit(Option<i32>),
     Type(String),
     Pwd,
     Cd(String),
     BuiltinLs,
     BuiltinHelp(&'static str),
     History(HistoryArgs),
+    Alias(Vec<String>),
+    Unalias(Vec<String>),
+    Source(Vec<String>),
+    Export(Vec<String>),
+    Set(Vec<String>),
+    Trap(Vec<String>),
+    Jobs(Vec<String>),
+    Fg(Vec<String>),
+    Bg(Vec<String>),
+    Wait(Vec<String>),
+    Kill(Vec<String>),
+    Disown(Vec<String>),
+    Exec(Vec<String>),
+    Times,
+    Ulimit(Vec<String>),
+    Umask(Vec<String>),
+    Parallel(Vec<String>),
+    Read(Vec<String>),
+    /* coproc NAME CMDS, from coproc [NAME] { CMDS; } */
+    Coproc(String, String),
+    /* NAME=VALUE ... */
+    Assign(Vec<(String, String)>),
+    /* NAME=VALUE ... CMD, the variables are only for the cmd */
+    WithVars(Vec<(String, String)>, Box<Cmd>),
     NotBuiltin(Vec<String>),
 }
 
+impl Cmd {
+    /* the cmds which are not builtins always run in a job */
+    pub fn is_builtin(&self) -> bool {
+        match self {
+            Cmd::NotBuiltin(_) => false,
+            Cmd::WithVars(_, cmd) => cmd.is_builtin(),
+            _ => true,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum HistoryArgs {
     Show(Option<usize>),
@@ -38,10 +119,15 @@ pub enum HistoryArgs {
 pub enum CmdParseFail {
     NoCommand,
     NotCommand(String),
+    NoSuchFile(String),
+    NotExecutable(String, &'static str),
     ExitArgsError,
     TypeArgsError,
     CdArgsError,
     HistoryArgsError,
+    UnaliasArgsError,
+    SourceArgsError,
+    CoprocArgsError,
     Never,
 }
 
@@ -52,13 +138,15 @@ pub enum Api<'a> {
     InputHistory,
     ReadHistoryFromFile(&'a str),
     WriteHistoryToFile(&'a str),
+    FindAlias(&'a str),
 }
 
 pub fn parse_cmd(mut cmd: Vec<String>) -> Result<Cmd, CmdParseFail> {
     if cmd.is_empty() {
         return Err(CmdParseFail::NoCommand);
     } else if cmd.len() >= 2 {
-        for builtin in BUILTIN_CMDS.iter() {
+        /* disown -h is an option */
+        for builtin in BUILTIN_CMDS.iter().filter(|builtin| **builtin != "disown") {
             if &cmd[0] == builtin && cmd[1] == "-h" {
                 return Ok(Cmd::BuiltinHelp(builtin));
             }
@@ -71,10 +159,10 @@ pub fn parse_cmd(mut cmd: Vec<String>) -> Result<Cmd, CmdParseFail> {
         }
         "exit" => {
             if cmd.len() == 1 {
-                Ok(Cmd::Exit(0))
+                Ok(Cmd::Exit(None))
             } else {
-                match cmd[1].parse::<i32>() {
-                    Ok(num) => Ok(Cmd::Exit(num)),
+                match cmd[1].parse::<i64>() {
+                    Ok(num) => Ok(Cmd::Exit(Some(num.rem_euclid(256) as i32))),
                     Err(_) => Err(CmdParseFail::ExitArgsError),
                 }
             }
@@ -119,19 +207,98 @@ pub fn parse_cmd(mut cmd: Vec<String>) -> Result<Cmd, CmdParseFail> {
                 Ok(Cmd::History(HistoryArgs::Show(None)))
             }
         }
-        _ => {
-            if let Some(_path) = drshell_env::if_executable(&cmd[0]) {
-                Ok(Cmd::NotBuiltin(cmd))
+        "alias" => {
+            cmd.remove(0);
+            Ok(Cmd::Alias(cmd))
+        }
+        "unalias" => {
+            if cmd.len() == 1 {
+                Err(CmdParseFail::UnaliasArgsError)
+            } else {
+                cmd.remove(0);
+                Ok(Cmd::Unalias(cmd))
+            }
+        }
+        "source" | "." => {
+            if cmd.len() == 1 {
+                Err(CmdParseFail::SourceArgsError)
             } else {
-                Err(CmdParseFail::NotCommand(cmd[0].clone()))
+                cmd.remove(0);
+                Ok(Cmd::Source(cmd))
             }
         }
+        "export" => {
+            cmd.remove(0);
+            Ok(Cmd::Export(cmd))
+        }
+        "set" => {
+            cmd.remove(0);
+            Ok(Cmd::Set(cmd))
+        }
+        "trap" => {
+            cmd.remove(0);
+            Ok(Cmd::Trap(cmd))
+        }
+        "jobs" => {
+            cmd.remove(0);
+            Ok(Cmd::Jobs(cmd))
+        }
+        "fg" => {
+            cmd.remove(0);
+            Ok(Cmd::Fg(cmd))
+        }
+        "bg" => {
+            cmd.remove(0);
+            Ok(Cmd::Bg(cmd))
+        }
+        "wait" => {
+            cmd.remove(0);
+            Ok(Cmd::Wait(cmd))
+        }
+        "kill" => {
+            cmd.remove(0);
+            Ok(Cmd::Kill(cmd))
+        }
+        "disown" => {
+            cmd.remove(0);
+            Ok(Cmd::Disown(cmd))
+        }
+        "exec" => {
+            cmd.remove(0);
+            Ok(Cmd::Exec(cmd))
+        }
+        "times" => Ok(Cmd::Times),
+        "ulimit" => {
+            cmd.remove(0);
+            Ok(Cmd::Ulimit(cmd))
+        }
+        "umask" => {
+            cmd.remove(0);
+            Ok(Cmd::Umask(cmd))
+        }
+        "parallel" => {
+            cmd.remove(0);
+            Ok(Cmd::Parallel(cmd))
+        }
+        "read" => {
+            cmd.remove(0);
+            Ok(Cmd::Read(cmd))
+        }
+        "coproc" => {
+            let mut args = cmd.into_iter().skip(1);
+            match (args.next(), args.next()) {
+                (Some(name), Some(cmds)) => Ok(Cmd::Coproc(name, cmds)),
+                _ => Err(CmdParseFail::CoprocArgsError),
+            }
+        }
+        /* the cmds are looked up when they run */
+        _ => Ok(Cmd::NotBuiltin(cmd)),
     }
 }
 
-pub fn parse_cmd_fail_process(reason: CmdParseFail) {
+pub fn parse_cmd_fail_process(reason: CmdParseFail) -> i32 {
     match reason {
-        CmdParseFail::NoCommand => {}
+        CmdParseFail::NoCommand => return 0,
         CmdParseFail::ExitArgsError => {
             eprintln!("no number of exiting!!!");
         }
@@ -143,15 +310,43 @@ pub fn parse_cmd_fail_process(reason: CmdParseFail) {
         }
         CmdParseFail::NotCommand(cmd) => {
             eprintln!("{}", cmd + ": command not found");
+            return 127;
+        }
+        CmdParseFail::NoSuchFile(cmd) => {
+            eprintln!("{}", cmd + ": No such file or directory");
+            return 127;
+        }
+        CmdParseFail::NotExecutable(cmd, reason) => {
+            eprintln!("{}: {}", cmd, reason);
+            return 126;
         }
         CmdParseFail::HistoryArgsError => {
             eprintln!("wrong args for history!!!");
         }
+        CmdParseFail::UnaliasArgsError => {
+            eprintln!("no name of an alias!!!");
+        }
+        CmdParseFail::SourceArgsError => {
+            eprintln!("no name of a file!!!");
+        }
+        CmdParseFail::CoprocArgsError => {
+            eprintln!("no command for the coproc!!!");
+        }
         CmdParseFail::Never => {}
     }
+    2
+}
+
+/* the message of an io error without the " (os error N)" */
+pub fn error_reason(err: &io::Error) -> String {
+    let reason = err.to_string();
+    match reason.find(" (os error") {
+        Some(index) => reason[..index].to_string(),
+        None => reason,
+    }
 }
 
-pub fn eval(cmd: Cmd) {
+pub fn eval(cmd: Cmd) -> i32 {
     match cmd {
         Cmd::Echo(str) => echo::echo(str),
         Cmd::Exit(num) => exit::exit(num),
@@ -162,94 +357,62 @@ pub fn eval(cmd: Cmd) {
         Cmd::BuiltinHelp(cmd) => help::builtinhelp(cmd),
         Cmd::History(args) => match args {
             HistoryArgs::Show(limit) => history::history(limit),
-            HistoryArgs::ReadFromFile(path) => history::read_from_file(path),
-            HistoryArgs::WriteToFile(path) => history::write_to_file(path),
+            HistoryArgs::ReadFromFile(path) => history::history_file(path, false),
+            HistoryArgs::WriteToFile(path) => history::history_file(path, true),
         },
-        Cmd::NotBuiltin(cmd) => {
-            let mut args = cmd.clone();
-            args.remove(0);
-            match Command::new(&cmd[0]).args(args).spawn() {
-                Ok(mut child) => {
-                    if let Err(err) = child.wait() {
-                        eprintln!("{}", err);
-                    }
-                }
-                Err(err) => {
-                    eprintln!("{}", err);
-                }
-            }
-        }
+        Cmd::Alias(args) => alias::alias(args),
+        Cmd::Unalias(args) => alias::unalias(args),
+        Cmd::Source(args) => source::source(args),
+        Cmd::Export(args) => export::export(args),
+        Cmd::Set(args) => set::set(args),
+        Cmd::Trap(args) => trap::trap(args),
+        Cmd::Jobs(args) => jobs::jobs(args),
+        Cmd::Fg(args) => jobs::fg(args),
+        Cmd::Bg(args) => jobs::bg(args),
+        Cmd::Wait(args) => jobs::wait(args),
+        Cmd::Kill(args) => kill::kill(args),
+        Cmd::Disown(args) => jobs::disown(args),
+        Cmd::Exec(args) => exec::exec(args),
+        Cmd::Times => times::times(),
+        Cmd::Ulimit(args) => ulimit::ulimit(args),
+        Cmd::Umask(args) => umask::umask(args),
+        Cmd::Parallel(args) => parallel::parallel(args),
+        Cmd::Read(args) => read::read(args),
+        Cmd::Coproc(name, cmds) => coproc::coproc(name, cmds),
+        Cmd::Assign(assignments) => export::assign(assignments),
+        Cmd::WithVars(assignments, cmd) => export::with_vars(assignments, *cmd),
+        /* the other cmds always run in a job, see pipline::Stage::new */
+        Cmd::NotBuiltin(_) => unreachable!("eval of a cmd which is not a builtin"),
     }
 }
 
-pub fn generate_cmd(cmd: Cmd) -> Command {
-    let mut command_generate: Command;
-    let command: String;
-    let mut args = vec![];
-
-    if let Cmd::NotBuiltin(mut cmd) = cmd {
-        command = cmd.remove(0);
-        for c in cmd {
-            args.push(c);
-        }
-    } else {
-        command = "drshell".to_string();
-        args.push("-c".to_string());
-
-        match cmd {
-            Cmd::Echo(str) => {
-                args.push("echo".to_string());
-                for s in str {
-                    args.push(s);
-                }
-            }
-            Cmd::Exit(num) => {
-                args.push("exit".to_string());
-                args.push(num.to_string());
-            }
-            Cmd::Type(name) => {
-                args.push("type".to_string());
-                args.push(name);
-            }
-            Cmd::Pwd => {
-                args.push("pwd".to_string());
-            }
-            Cmd::Cd(dir) => {
-                args.push("cd".to_string());
-                args.push(dir);
-            }
-            Cmd::BuiltinLs => {
-                args.push("lsbuiltin".to_string());
-            }
-            Cmd::BuiltinHelp(cmd) => {
-                args.push(cmd.to_string());
-                args.push("-h".to_string());
-            }
-            Cmd::History(args_history) => {
-                args.push("history".to_string());
-                match args_history {
-                    HistoryArgs::Show(limit) => {
-                        if let Some(l) = limit {
-                            args.push(l.to_string());
-                        }
-                    }
-                    HistoryArgs::ReadFromFile(path) => {
-                        args.push("-r".to_string());
-                        args.push(path);
-                    }
-                    HistoryArgs::WriteToFile(path) => {
-                        args.push("-w".to_string());
-                        args.push(path);
-                    }
-                }
-            }
-            _ => {}
+/* the cmd runs from the path found, with its name as argv[0] */
+pub fn generate_cmd(mut cmd: Vec<String>) -> Result<Command, CmdParseFail> {
+    let sigpipe_default = sigpipe_default();
+    let name = cmd.remove(0);
+    let path = match drshell_env::find_command(&name) {
+        Ok(path) => path,
+        Err(ExeFindFail::NotFound) => return Err(CmdParseFail::NotCommand(name)),
+        Err(ExeFindFail::NoSuchFile) => return Err(CmdParseFail::NoSuchFile(name)),
+        Err(ExeFindFail::IsDirectory) => {
+            return Err(CmdParseFail::NotExecutable(name, "Is a directory"))
+        }
+        Err(ExeFindFail::PermissionDenied) => {
+            return Err(CmdParseFail::NotExecutable(name, "Permission denied"))
+        }
+    };
+    let mut command_generate = Command::new(path);
+    command_generate.arg0(name).args(cmd);
+    if sigpipe_default {
+        unsafe {
+            command_generate.pre_exec(|| {
+                libc::signal(libc::SIGPIPE, libc::SIG_DFL);
+                Ok(())
+            });
         }
     }
-    command_generate = std::process::Command::new(command);
-    command_generate.args(args);
 
-    command_generate
+    Ok(command_generate)
 }
 
 pub fn api(api: Api) -> Option<String> {
@@ -260,8 +423,100 @@ pub fn api(api: Api) -> Option<String> {
         Api::InputHistory => {
             return history::input_history();
         }
-        Api::ReadHistoryFromFile(file) => history::read_from_file(file.to_string()),
-        Api::WriteHistoryToFile(file) => history::write_to_file(file.to_string()),
+        /* the shell starts without a history file */
+        Api::ReadHistoryFromFile(file) => match history::read_from_file(file) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => {
+                eprintln!("drshell: {}: {}", file, error_reason(&err));
+            }
+            _ => {}
+        },
+        Api::WriteHistoryToFile(file) => {
+            if let Err(err) = history::write_to_file(file) {
+                eprintln!("drshell: {}: {}", file, error_reason(&err));
+            }
+        }
+        Api::FindAlias(name) => {
+            return alias::find_alias(name);
+        }
     }
     None
 }
+
+pub fn exit_shell(status: i32) -> ! {
+    exit::exit_shell(status)
+}
+
+pub fn reset_stopped_jobs_warning() {
+    exit::reset_stopped_jobs_warning();
+}
+
+pub fn hangup() -> ! {
+    exit::hangup()
+}
+
+pub fn run_pending_traps() {
+    trap::run_pending_traps();
+}
+
+pub fn run_err_trap() {
+    trap::run_err_trap();
+}
+
+pub fn run_exit_trap() {
+    trap::run_exit_trap();
+}
+
+pub fn reset_traps() {
+    trap::reset_traps();
+}
+
+/* the rust runtime ignores SIGPIPE, the cmds get the default unless it is trapped with '' */
+pub fn sigpipe_default() -> bool {
+    !trap::ignored(libc::SIGPIPE)
+}
+
+/* the output left in the buffer of a builtin which couldn't write it is dropped */
+pub fn eval_in_shell(cmd: Cmd) -> i32 {
+    let status = eval(cmd);
+    match io::stdout().flush() {
+        Ok(()) => status,
+        Err(err) => {
+            discard_stdout();
+            if status == 0 {
+                write_fail_process(&err)
+            } else {
+                status
+            }
+        }
+    }
+}
+
+/* a builtin writing to a closed pipe stops quietly, with the status of SIGPIPE */
+pub fn write_fail_process(err: &io::Error) -> i32 {
+    if err.kind() == io::ErrorKind::BrokenPipe {
+        return 128 + libc::SIGPIPE;
+    }
+    eprintln!("drshell: write error: {}", error_reason(err));
+    1
+}
+
+/* the output left in the buffer is flushed to /dev/null, so it doesn't go to the
+ * restored stdout later, then the stdout is put back */
+fn discard_stdout() {
+    let Ok(null) = OpenOptions::new().write(true).open("/dev/null") else {
+        return;
+    };
+    let saved = unsafe { libc::fcntl(1, libc::F_DUPFD_CLOEXEC, 10) };
+    unsafe { libc::dup2(null.as_raw_fd(), 1) };
+    let _ = io::stdout().flush();
+    if saved >= 0 {
+        unsafe {
+            libc::dup2(saved, 1);
+            libc::close(saved);
+        }
+    }
+}
+
+pub fn alias_names() -> Vec<String> {
+    alias::alias_names()
+}
@@ -0,0 +1,85 @@
+use super::super::expand;
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+lazy_static! {
+    static ref ALIASES: Mutex<BTreeMap<String, String>> = Mutex::new(BTreeMap::new());
+}
+
+fn print_alias(name: &str, value: &str) -> io::Result<()> {
+    writeln!(io::stdout(), "alias {}={}", name, expand::quote(value))
+}
+
+fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name
+            .chars()
+            .any(|c| c.is_whitespace() || "/$`'\"\\|&;<>()=".contains(c))
+}
+
+pub fn alias(args: Vec<String>) -> i32 {
+    let mut aliases = ALIASES.lock().unwrap();
+    let mut status = 0;
+
+    if args.is_empty() || args.iter().all(|arg| arg == "-p") {
+        for (name, value) in aliases.iter() {
+            if let Err(err) = print_alias(name, value) {
+                return super::write_fail_process(&err);
+            }
+        }
+        return 0;
+    }
+
+    for arg in args {
+        if arg == "-p" {
+            continue;
+        }
+        match arg.split_once('=') {
+            Some((name, value)) => {
+                if valid_name(name) {
+                    aliases.insert(name.to_string(), value.to_string());
+                } else {
+                    eprintln!("alias: {}: invalid alias name", name);
+                    status = 1;
+                }
+            }
+            None => match aliases.get(&arg) {
+                Some(value) => {
+                    if let Err(err) = print_alias(&arg, value) {
+                        return super::write_fail_process(&err);
+                    }
+                }
+                None => {
+                    eprintln!("alias: {}: not found", arg);
+                    status = 1;
+                }
+            },
+        }
+    }
+    status
+}
+
+pub fn unalias(args: Vec<String>) -> i32 {
+    let mut aliases = ALIASES.lock().unwrap();
+    let mut status = 0;
+
+    for arg in args {
+        if arg == "-a" {
+            aliases.clear();
+        } else if aliases.remove(&arg).is_none() {
+            eprintln!("unalias: {}: not found", arg);
+            status = 1;
+        }
+    }
+    status
+}
+
+pub fn find_alias(name: &str) -> Option<String> {
+    ALIASES.lock().unwrap().get(name).cloned()
+}
+
+pub fn alias_names() -> Vec<String> {
+    ALIASES.lock().unwrap().keys().cloned().collect()
+}
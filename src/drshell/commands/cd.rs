@@ -1,19 +1,19 @@
 use std::env;
 use std::sync::Mutex;
 
-pub fn cd(mut dir: String) {
+pub fn cd(mut dir: String) -> i32 {
     static LAST_DIR: Mutex<String> = Mutex::new(String::new());
 
     match dir.as_str() {
         "" => {
             eprintln!("{}", "cd: ".to_string() + ": No such file or directory");
-            return;
+            return 1;
         }
         "-" => {
             dir = LAST_DIR.lock().unwrap().clone();
             if dir.is_empty() {
                 eprintln!("no previous worked directory");
-                return;
+                return 1;
             }
         }
         "~" => {
@@ -24,7 +24,7 @@ pub fn cd(mut dir: String) {
                         "{}",
                         "can't get the directory of home: ".to_string() + &err.to_string()
                     );
-                    return;
+                    return 1;
                 }
             }
         }
@@ -38,7 +38,7 @@ pub fn cd(mut dir: String) {
                             "{}",
                             "can't get the directory of home: ".to_string() + &err.to_string()
                         );
-                        return;
+                        return 1;
                     }
                 };
                 let subdir: String = dir.chars().skip(1).collect();
@@ -51,7 +51,7 @@ pub fn cd(mut dir: String) {
             Ok(path) => path,
             Err(_osstring) => {
                 eprintln!("can't resolve the current working directory");
-                return;
+                return 1;
             }
         },
         Err(err) => {
@@ -59,7 +59,7 @@ pub fn cd(mut dir: String) {
                 "{}",
                 "can't resolve the current working directory: ".to_string() + &err.to_string()
             );
-            return;
+            return 1;
         }
     };
     match env::set_current_dir(&dir) {
@@ -68,9 +68,11 @@ pub fn cd(mut dir: String) {
                 "{}",
                 "cd: ".to_string() + &dir + ": No such file or directory"
             );
+            1
         }
         Ok(_ok) => {
             *LAST_DIR.lock().unwrap() = last_dir;
+            0
         }
     }
 }
@@ -0,0 +1,108 @@
+use super::super::jobs::{self as job_table, Job};
+use super::super::operators;
+use super::super::script;
+use super::super::vars;
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::os::fd::{AsRawFd, IntoRawFd};
+use std::sync::Mutex;
+
+/* an end of a pipe, with the device and the inode which tell whether the fd has been
+ * closed and reused since */
+#[derive(Clone, Copy)]
+struct PipeEnd {
+    fd: i32,
+    id: (u64, u64),
+}
+
+fn pipe_id(fd: i32) -> Option<(u64, u64)> {
+    let mut stat: libc::stat = unsafe { std::mem::zeroed() };
+    if unsafe { libc::fstat(fd, &mut stat) } < 0 {
+        return None;
+    }
+    Some((stat.st_dev, stat.st_ino))
+}
+
+impl PipeEnd {
+    fn new(fd: i32) -> Self {
+        Self {
+            fd,
+            id: pipe_id(fd).unwrap_or_default(),
+        }
+    }
+
+    /* the fds closed by the user are left alone */
+    fn close(self) {
+        if pipe_id(self.fd) == Some(self.id) {
+            unsafe { libc::close(self.fd) };
+        }
+    }
+}
+
+lazy_static! {
+    /* the shell's ends of the pipes of each coproc, read then write */
+    static ref COPROC_FDS: Mutex<BTreeMap<String, [PipeEnd; 2]>> = Mutex::new(BTreeMap::new());
+}
+
+/* run the cmds in the background, reading from NAME[1] and writing to NAME[0] */
+pub fn coproc(name: String, cmds: String) -> i32 {
+    let pipes = operators::pipe().and_then(|(stdin, to_coproc)| {
+        let (from_coproc, stdout) = operators::pipe()?;
+        Ok((
+            stdin,
+            stdout,
+            operators::high_fd(from_coproc)?,
+            operators::high_fd(to_coproc)?,
+        ))
+    });
+    let (stdin, stdout, from_coproc, to_coproc) = match pipes {
+        Ok(pipes) => pipes,
+        Err(err) => {
+            eprintln!("coproc: {}", super::error_reason(&err));
+            return 1;
+        }
+    };
+
+    let shell_fds: Vec<PipeEnd> = COPROC_FDS
+        .lock()
+        .unwrap()
+        .values()
+        .flatten()
+        .copied()
+        .chain([
+            PipeEnd::new(from_coproc.as_raw_fd()),
+            PipeEnd::new(to_coproc.as_raw_fd()),
+        ])
+        .collect();
+    let streams = [(0, Some(stdin.as_raw_fd())), (1, Some(stdout.as_raw_fd()))];
+    /* the coproc only gets EOF when the shell closes its end, so the forked shell
+     * doesn't keep the ends of the shell, nor the ones of the other coprocs */
+    let forked = job_table::fork_shell(0, false, || {
+        operators::dup_streams(&streams);
+        for end in shell_fds {
+            end.close();
+        }
+        script::run_line(&cmds)
+    });
+    let pid = match forked {
+        Ok(pid) => pid,
+        Err(err) => {
+            eprintln!("drshell: fork: {}", super::error_reason(&err));
+            return 1;
+        }
+    };
+
+    let fds = [from_coproc.into_raw_fd(), to_coproc.into_raw_fd()];
+    /* a new coproc with the same name replaces the old pipes */
+    let ends = fds.map(PipeEnd::new);
+    if let Some(old) = COPROC_FDS.lock().unwrap().insert(name.clone(), ends) {
+        for end in old {
+            end.close();
+        }
+    }
+    vars::set_array(&name, fds.iter().map(|fd| fd.to_string()).collect());
+    vars::set_var(&(name.clone() + "_PID"), &pid.to_string());
+    let command = format!("coproc {} {{ {}; }}", name, cmds);
+    job_table::run_background(Job::new(pid, vec![pid], 0, command));
+    0
+}
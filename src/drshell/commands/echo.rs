@@ -1,6 +1,7 @@
-use std::io;
+use std::io::{self, Write};
 
-pub fn echo(mut str: Vec<String>) {
+pub fn echo(str: Vec<String>) -> i32 {
+    let mut stdout = io::stdout();
     if str.is_empty() {
         let mut line = String::new();
         let stdin = io::stdin();
@@ -8,24 +9,23 @@ pub fn echo(mut str: Vec<String>) {
             match stdin.read_line(&mut line) {
                 Ok(num) => {
                     if num == 0 {
-                        return;
+                        return 0;
                     }
                 }
                 Err(_) => {
                     eprintln!("read stdin error!!!");
-                    return;
+                    return 1;
                 }
             }
-            print!("{line}");
+            if let Err(err) = stdout.write_all(line.as_bytes()) {
+                return super::write_fail_process(&err);
+            }
             line.clear();
         }
     } else {
-        let last = str.pop();
-        for s in str {
-            print!("{s} ");
-        }
-        if let Some(last) = last {
-            println!("{last}");
+        match writeln!(stdout, "{}", str.join(" ")) {
+            Ok(()) => 0,
+            Err(err) => super::write_fail_process(&err),
         }
     }
 }
@@ -0,0 +1,23 @@
+use super::super::jobs as job_table;
+use super::super::options;
+
+/* the redirections of exec are kept by the shell, see script::execute_cmds_and_opts */
+pub fn exec(args: Vec<String>) -> i32 {
+    let Some(name) = args.first().cloned() else {
+        return 0;
+    };
+
+    let status = match super::generate_cmd(args) {
+        Ok(mut cmd) => {
+            let err = job_table::exec(&mut cmd);
+            eprintln!("drshell: exec: {}: {}", name, super::error_reason(&err));
+            126
+        }
+        Err(reason) => super::parse_cmd_fail_process(reason),
+    };
+    /* a shell which can't exec has nothing left to run */
+    if !options::interactive() {
+        super::exit_shell(status);
+    }
+    status
+}
@@ -1,14 +1,48 @@
+use super::super::jobs;
+use super::super::options;
+use super::super::vars;
 use super::Api;
 use std::env;
+use std::io::{self, Write};
 use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-pub fn exit(num: i32) {
-    if num != 0 && num != 1 {
-        eprintln!("need number 0 or 1 to exit!!!");
-        return;
+/* the warning about the stopped jobs since the last cmd other than exit */
+static STOPPED_JOBS_WARNED: AtomicBool = AtomicBool::new(false);
+
+pub fn exit(num: Option<i32>) -> i32 {
+    let num = num.unwrap_or_else(vars::status);
+    /* the second exit leaves the stopped jobs */
+    if jobs::has_stopped_jobs() && !STOPPED_JOBS_WARNED.swap(true, Ordering::SeqCst) {
+        eprintln!("There are stopped jobs.");
+        return 1;
+    }
+    exit_shell(num);
+}
+
+/* any other cmd between two exits makes the second one warn again */
+pub fn reset_stopped_jobs_warning() {
+    STOPPED_JOBS_WARNED.store(false, Ordering::SeqCst);
+}
+
+/* the terminal is closed, the jobs get SIGHUP and the history is saved */
+pub fn hangup() -> ! {
+    jobs::hangup();
+    exit_shell(128 + libc::SIGHUP);
+}
+
+pub fn exit_shell(num: i32) -> ! {
+    super::run_exit_trap();
+    if jobs::subshell() {
+        let _ = io::stdout().flush();
+        process::exit(num);
     }
-    if let Ok(home) = env::var("HOME") {
-        super::api(Api::WriteHistoryToFile((home + "/.drhistory").as_str()));
+    jobs::release_terminal();
+    /* a script or -c must not overwrite the history of an interactive shell */
+    if options::interactive() {
+        if let Ok(home) = env::var("HOME") {
+            super::api(Api::WriteHistoryToFile((home + "/.drhistory").as_str()));
+        }
     }
     process::exit(num);
 }
@@ -0,0 +1,57 @@
+use super::super::expand;
+use super::super::vars;
+use super::Cmd;
+use std::io::{self, Write};
+
+pub fn export(args: Vec<String>) -> i32 {
+    let mut status = 0;
+
+    if args.is_empty() || args.iter().all(|arg| arg == "-p") {
+        let mut stdout = io::stdout();
+        for (name, value) in vars::exported_vars() {
+            if let Err(err) = writeln!(stdout, "export {}={}", name, expand::quote(&value)) {
+                return super::write_fail_process(&err);
+            }
+        }
+        return 0;
+    }
+
+    for arg in args {
+        if arg == "-p" {
+            continue;
+        }
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (arg.as_str(), None),
+        };
+        if vars::valid_name(name) {
+            vars::export_var(name, value);
+        } else {
+            eprintln!("export: {}: not a valid identifier", name);
+            status = 1;
+        }
+    }
+
+    status
+}
+
+pub fn assign(assignments: Vec<(String, String)>) -> i32 {
+    for (name, value) in assignments {
+        vars::set_var(&name, &value);
+    }
+    0
+}
+
+/* NAME=VALUE ... CMD, the variables are set only while the builtin runs */
+pub fn with_vars(assignments: Vec<(String, String)>, cmd: Cmd) -> i32 {
+    let saved: Vec<(String, Option<String>)> = assignments
+        .iter()
+        .map(|(name, _)| (name.clone(), vars::get_var(name)))
+        .collect();
+    assign(assignments);
+    let status = super::eval(cmd);
+    for (name, value) in saved.into_iter().rev() {
+        vars::restore_var(&name, value);
+    }
+    status
+}
@@ -1,41 +1,55 @@
-pub fn builtinhelp(cmd: &str) {
+use std::io::{self, Write};
+
+pub fn builtinhelp(cmd: &str) -> i32 {
+    match print_help(cmd, &mut io::stdout()) {
+        Ok(()) => 0,
+        Err(err) => super::write_fail_process(&err),
+    }
+}
+
+fn print_help(cmd: &str, out: &mut impl Write) -> io::Result<()> {
     match cmd {
         "echo" => {
-            println!(
+            writeln!(
+                out,
                 "Description:
 echo the words
 Usage:
 echo <STRING>"
-            );
+            )?;
         }
         "exit" => {
-            println!(
+            writeln!(
+                out,
                 "Description:
 terminate the shell progress
 Usage:
-exit <NUMBER>
+exit [NUMBER]
 Details:
-the number can be 0 or 1"
-            );
+the status is the number modulo 256, or the status of the last command without it"
+            )?;
         }
         "type" => {
-            println!(
+            writeln!(
+                out,
                 "Description:
 show the command if builtin or other
 Usage:
 type <COMMAND>"
-            );
+            )?;
         }
         "pwd" => {
-            println!(
+            writeln!(
+                out,
                 "Description:
 print the current working directory
 Usage:
 pwd"
-            );
+            )?;
         }
         "cd" => {
-            println!(
+            writeln!(
+                out,
                 "Description:
 change the current working directory
 Usage:
@@ -43,18 +57,20 @@ cd <DIRECTORY>
 Details:
 1. - the last work directory
 2. ~ the home directory"
-            );
+            )?;
         }
         "lsbuiltin" => {
-            println!(
+            writeln!(
+                out,
                 "Description:
 list all the builtin commands
 Usage:
 lsbuiltin"
-            );
+            )?;
         }
         "history" => {
-            println!(
+            writeln!(
+                out,
                 "Description:
 1. list the previously executed commands
 2. write the history to a file
@@ -62,8 +78,246 @@ lsbuiltin"
 4. append the history to a file
 Usage:
 history <LIMIT> | <-w FILE> | <-r FILE> | <-a FILE>"
-            );
+            )?;
+        }
+        "alias" => {
+            writeln!(
+                out,
+                "Description:
+define or display aliases
+Usage:
+alias [-p] [NAME[=VALUE] ...]
+Details:
+1. without arguments or with -p, list all the aliases
+2. if VALUE ends with a blank, the next word is checked for aliases too"
+            )?;
+        }
+        "unalias" => {
+            writeln!(
+                out,
+                "Description:
+remove aliases
+Usage:
+unalias <NAME ...> | <-a>"
+            )?;
+        }
+        "source" | "." => {
+            writeln!(
+                out,
+                "Description:
+run the commands of a file in the current shell
+Usage:
+source <FILE> [ARGS ...]
+. <FILE> [ARGS ...]
+Details:
+1. a FILE without a slash is searched in $PATH, then in the current directory
+2. the ARGS become the positional params while the file runs"
+            )?;
+        }
+        "export" => {
+            writeln!(
+                out,
+                "Description:
+export variables to the environment of the commands
+Usage:
+export [-p] [NAME[=VALUE] ...]
+Details:
+without arguments or with -p, list all the exported variables"
+            )?;
+        }
+        "set" => {
+            writeln!(
+                out,
+                "Description:
+set the shell options or the positional params
+Usage:
+set [-euCxv] [+euCxv] [-o NAME] [+o NAME] [-- | -] [ARGS ...]
+Details:
+1. - enables and + disables an option
+2. -e (errexit) exits when a command fails, except in the && and || lists and after !
+3. -u (nounset) treats the expansions of the unset variables as errors
+4. -o pipefail makes the status of a pipeline the one of its last failing command
+5. -C (noclobber) prevents > from overwriting the files, >| overwrites them anyway
+6. -x (xtrace) prints the expanded commands to stderr after $PS4
+7. -v (verbose) prints the input lines to stderr as they are read
+8. -o ignoreeof keeps Ctrl-D from exiting the interactive shell, until $IGNOREEOF
+   of them in a row (10 by default)
+9. -o multios writes an output fd redirected more than once to all its files
+10. without arguments, list all the variables, -o or +o alone lists the options
+11. -- sets the positional params to ARGS, - also turns off -x and -v and keeps
+    the params without ARGS"
+            )?;
+        }
+        "trap" => {
+            writeln!(
+                out,
+                "Description:
+run a command when the shell receives a signal
+Usage:
+trap [-lp] [ACTION SIGNAL ...] | [- SIGNAL ...]
+Details:
+1. the SIGNAL can be a name (INT or SIGINT), a number, EXIT or ERR
+2. EXIT runs when the shell exits, ERR runs when a command fails
+3. an empty ACTION ignores the signal, - resets it
+4. without arguments or with -p, list the traps, -l lists the signals"
+            )?;
+        }
+        "jobs" => {
+            writeln!(
+                out,
+                "Description:
+display the jobs
+Usage:
+jobs [-lp] [JOBSPEC ...]
+Details:
+1. JOBSPEC is %n, %+ or %% (the current job), %- (the previous job), %string or %?string
+2. -l shows the process group ids too, -p shows only them"
+            )?;
+        }
+        "fg" => {
+            writeln!(
+                out,
+                "Description:
+continue a job in the foreground
+Usage:
+fg [JOBSPEC]"
+            )?;
+        }
+        "bg" => {
+            writeln!(
+                out,
+                "Description:
+continue stopped jobs in the background
+Usage:
+bg [JOBSPEC ...]"
+            )?;
+        }
+        "wait" => {
+            writeln!(
+                out,
+                "Description:
+wait for jobs to finish and return their status
+Usage:
+wait [-n] [PID | JOBSPEC ...]
+Details:
+1. without arguments, wait for all the running jobs
+2. -n waits for the next job to finish"
+            )?;
+        }
+        "kill" => {
+            writeln!(
+                out,
+                "Description:
+send a signal to processes or jobs
+Usage:
+kill [-s SIGNAL | -n NUM | -SIGNAL] <PID | JOBSPEC ...>
+kill -l [SIGNAL | STATUS]
+Details:
+1. the default signal is TERM
+2. -l lists the signals, or converts the names and the numbers"
+            )?;
+        }
+        "disown" => {
+            writeln!(
+                out,
+                "Description:
+remove jobs from the table, so they don't get SIGHUP when the shell exits
+Usage:
+disown [-h] [-a | JOBSPEC ...]
+Details:
+1. without JOBSPEC, the current job
+2. -h keeps the jobs in the table, but they don't get SIGHUP
+3. -a works on all the jobs"
+            )?;
+        }
+        "exec" => {
+            writeln!(
+                out,
+                "Description:
+replace the shell with a command, or keep the redirections in the shell
+Usage:
+exec [COMMAND [ARGS ...]] [REDIRECTIONS]
+Details:
+1. without COMMAND, the redirections like 3> FILE or 3>&- stay in the shell
+2. the shell exits if the command can't be executed, unless it is interactive"
+            )?;
+        }
+        "times" => {
+            writeln!(
+                out,
+                "Description:
+print the user and system times of the shell, then of its children
+Usage:
+times"
+            )?;
+        }
+        "ulimit" => {
+            writeln!(
+                out,
+                "Description:
+show or set the resource limits of the shell and of the commands it runs
+Usage:
+ulimit [-S | -H] [-a | -c | -f | -n | -s | -t | -v ...] [LIMIT]
+Details:
+1. -c core file size in blocks, -f file size in blocks (the default), -n open files,
+-s stack size in kbytes, -t cpu time in seconds, -v virtual memory in kbytes
+2. -a shows all the limits
+3. -S sets or shows the soft limit, -H the hard limit, without them both are set
+and the soft one is shown
+4. LIMIT is a number, unlimited, soft or hard"
+            )?;
+        }
+        "umask" => {
+            writeln!(
+                out,
+                "Description:
+show or set the file mode mask of the shell and of the commands it runs
+Usage:
+umask [-p] [-S] [MODE]
+Details:
+1. MODE is an octal number like 022, or symbolic like u=rwx,g=rx,o= or g-w
+2. -S shows the mask as the allowed permissions, like u=rwx,g=rx,o=rx
+3. -p shows the mask as a umask command"
+            )?;
+        }
+        "parallel" => {
+            writeln!(
+                out,
+                "Description:
+run a command for each item, several at once, and print the outputs in order
+Usage:
+parallel [-j N] [-e] COMMAND [ARGS ...] [::: ITEMS ...]
+Details:
+1. {{}} in the args is replaced by the item, otherwise the item is the last arg
+2. without :::, the items are the lines of stdin
+3. -j runs at most N jobs at once, the number of cpus by default
+4. -e starts no more jobs after a job fails
+5. each job's stdout and stderr are printed when the jobs before it are done,
+then its status if it failed
+6. the status is the number of failed jobs, at most 101"
+            )?;
+        }
+        "read" => {
+            writeln!(
+                out,
+                "Description:
+read a line and split it by $IFS into variables
+Usage:
+read [-r] [-s] [-e] [-p PROMPT] [-t TIMEOUT] [-n N] [-d DELIM] [-u FD] [-a ARRAY] [NAME ...]
+Details:
+1. each NAME gets a field, the last one gets the rest of the line,
+without NAME the line goes to REPLY
+2. -r keeps the backslashes, otherwise a backslash escapes the next char
+and joins the lines
+3. -p prints PROMPT when the input is a terminal, -s doesn't echo the input
+and -e edits the line
+4. -t stops after TIMEOUT seconds, -t 0 only tells whether there is input
+5. -n reads N chars, -d reads until the first char of DELIM instead of a new line
+6. -u reads the fd FD instead of stdin, -a puts the fields in ARRAY
+7. the status is 1 at the end of the input and 142 after the timeout"
+            )?;
         }
         &_ => {}
     }
+    Ok(())
 }
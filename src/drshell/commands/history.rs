@@ -1,7 +1,6 @@
+use super::super::vars;
 use lazy_static::lazy_static;
 use std::fs;
-use std::io::Read;
-use std::path::Path;
 use std::{
     io::{self, Write},
     sync::Mutex,
@@ -34,7 +33,7 @@ lazy_static! {
     });
 }
 
-pub fn history(limit: Option<usize>) {
+pub fn history(limit: Option<usize>) -> i32 {
     let history = HISTORY_CMDS.lock().unwrap();
 
     let len = history.len();
@@ -43,11 +42,15 @@ pub fn history(limit: Option<usize>) {
         n = limit.min(n);
     }
 
+    let mut stdout = io::stdout();
     for (i, h) in history.iter().enumerate() {
         if i >= len - n {
-            println!("{} {}", i + 1, h);
+            if let Err(err) = writeln!(stdout, "{} {}", i + 1, h) {
+                return super::write_fail_process(&err);
+            }
         }
     }
+    0
 }
 
 pub fn save_history(h: &str) {
@@ -85,7 +88,7 @@ pub fn display_previous_cmd() {
     if !status.in_history_mode {
         print!("{c}");
     } else {
-        print!("\x1b[2K\r$ {c}");
+        print!("\x1b[2K\r{}{c}", vars::prompt());
     }
     if let Err(e) = io::stdout().flush() {
         eprint!("\x1b[2K\r{e}");
@@ -118,7 +121,7 @@ pub fn display_next_cmd() {
     if !status.in_history_mode {
         print!("{c}");
     } else {
-        print!("\x1b[2K\r$ {c}");
+        print!("\x1b[2K\r{}{c}", vars::prompt());
     }
     if let Err(e) = io::stdout().flush() {
         eprint!("\x1b[2K\r{e}");
@@ -145,21 +148,11 @@ pub fn input_history() -> Option<String> {
     }
 }
 
-pub fn read_from_file(file: String) {
+/* a missing file is reported, the shell ignores it at startup */
+pub fn read_from_file(file: &str) -> io::Result<()> {
+    let buffer = fs::read_to_string(file)?;
     let mut status = HISTORY_STATUS.lock().unwrap();
     let mut history = HISTORY_CMDS.lock().unwrap();
-    let mut buffer = String::new();
-
-    let path = Path::new(&file);
-
-    if path.exists() {
-        fs::OpenOptions::new()
-            .read(true)
-            .open(path)
-            .expect("can't open the file!!!")
-            .read_to_string(&mut buffer)
-            .expect("can't read from the file!!!");
-    }
 
     for line in buffer.lines() {
         history.push(line.to_string());
@@ -177,9 +170,10 @@ pub fn read_from_file(file: String) {
         in_history_mode: false,
         last_direction: Direction::None,
     };
+    Ok(())
 }
 
-pub fn write_to_file(file: String) {
+pub fn write_to_file(file: &str) -> io::Result<()> {
     let history = HISTORY_CMDS.lock().unwrap();
     let mut buffer = String::new();
 
@@ -188,18 +182,21 @@ pub fn write_to_file(file: String) {
         buffer += "\n";
     }
 
-    let path = Path::new(&file);
-    if !path.exists() {
-        if let Err(err) = fs::write(path, "") {
-            eprintln!("{}", err);
+    fs::write(file, buffer)
+}
+
+/* history -r and history -w */
+pub fn history_file(file: String, write: bool) -> i32 {
+    let result = if write {
+        write_to_file(&file)
+    } else {
+        read_from_file(&file)
+    };
+    match result {
+        Ok(()) => 0,
+        Err(err) => {
+            eprintln!("history: {}: {}", file, super::error_reason(&err));
+            1
         }
     }
-
-    fs::OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(path)
-        .expect("can't open the file!!!")
-        .write_all(buffer.as_bytes())
-        .expect("can't write to the file!!!");
 }
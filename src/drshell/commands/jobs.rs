@@ -0,0 +1,201 @@
+use super::super::jobs::{self as job_table, JobFindFail};
+use super::super::signals;
+use std::io::{self, Write};
+
+/* %n, %+, %-, %string or n */
+fn find_job(builtin: &str, spec: &str) -> Option<usize> {
+    match job_table::find_job(spec) {
+        Ok(id) => Some(id),
+        Err(JobFindFail::NoJob) => {
+            eprintln!("{}: {}: no such job", builtin, spec);
+            None
+        }
+        Err(JobFindFail::Ambiguous) => {
+            eprintln!("{}: {}: ambiguous job spec", builtin, spec);
+            None
+        }
+    }
+}
+
+pub fn jobs(args: Vec<String>) -> i32 {
+    let mut long = false;
+    let mut pids = false;
+    let mut specs = vec![];
+    let mut status = 0;
+
+    for arg in args {
+        match arg.as_str() {
+            "-l" => long = true,
+            "-p" => pids = true,
+            _ => specs.push(arg),
+        }
+    }
+
+    job_table::update();
+    let ids = if specs.is_empty() {
+        job_table::job_ids()
+    } else {
+        let mut ids = vec![];
+        for spec in specs {
+            match find_job("jobs", &spec) {
+                Some(id) => ids.push(id),
+                None => status = 1,
+            }
+        }
+        ids
+    };
+    for id in ids {
+        let printed = if pids {
+            match job_table::get_job(id) {
+                Some(job) => writeln!(io::stdout(), "{}", job.pgid()),
+                None => Ok(()),
+            }
+        } else {
+            job_table::print_job(id, long)
+        };
+        if let Err(err) = printed {
+            return super::write_fail_process(&err);
+        }
+    }
+    status
+}
+
+pub fn fg(args: Vec<String>) -> i32 {
+    if !job_table::job_control() {
+        eprintln!("fg: no job control");
+        return 1;
+    }
+    let spec = args.first().map(String::as_str).unwrap_or("%+");
+    match find_job("fg", spec) {
+        Some(id) => job_table::foreground(id),
+        None => 1,
+    }
+}
+
+pub fn bg(args: Vec<String>) -> i32 {
+    if !job_table::job_control() {
+        eprintln!("bg: no job control");
+        return 1;
+    }
+    let specs = if args.is_empty() {
+        vec!["%+".to_string()]
+    } else {
+        args
+    };
+    let mut status = 0;
+
+    for spec in specs {
+        let Some(id) = find_job("bg", &spec) else {
+            status = 1;
+            continue;
+        };
+        if job_table::get_job(id).is_some_and(|job| job.stopped()) {
+            job_table::background(id);
+        } else {
+            eprintln!("bg: job {} already in background", id);
+        }
+    }
+    status
+}
+
+pub fn disown(args: Vec<String>) -> i32 {
+    let mut nohup = false;
+    let mut all = false;
+    let mut specs = vec![];
+    let mut status = 0;
+
+    for arg in args {
+        match arg.as_str() {
+            "-h" => nohup = true,
+            "-a" => all = true,
+            _ => specs.push(arg),
+        }
+    }
+
+    let ids = if all {
+        job_table::job_ids()
+    } else {
+        if specs.is_empty() {
+            specs.push("%+".to_string());
+        }
+        let mut ids = vec![];
+        for spec in specs {
+            match find_job("disown", &spec) {
+                Some(id) => ids.push(id),
+                None => status = 1,
+            }
+        }
+        ids
+    };
+    for id in ids {
+        job_table::disown(id, nohup);
+    }
+    status
+}
+
+/* Ctrl-C stops waiting, after the echoed ^C */
+fn interrupted() -> i32 {
+    eprintln!();
+    130
+}
+
+pub fn wait(args: Vec<String>) -> i32 {
+    let mut any = false;
+    let mut targets = vec![];
+
+    for arg in args {
+        if arg == "-n" {
+            any = true;
+        } else {
+            targets.push(arg);
+        }
+    }
+
+    if any {
+        return match job_table::wait_any() {
+            Some(id) => job_table::wait_for(id),
+            None if signals::interrupted() => interrupted(),
+            None => 127,
+        };
+    }
+    if targets.is_empty() {
+        for id in job_table::job_ids() {
+            if !job_table::get_job(id).is_some_and(|job| job.stopped()) {
+                job_table::wait_for(id);
+            }
+            if signals::interrupted() {
+                return interrupted();
+            }
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for target in targets {
+        let id = if target.starts_with('%') {
+            find_job("wait", &target)
+        } else {
+            match target.parse::<i32>() {
+                Ok(pid) => {
+                    let id = job_table::find_job_by_pid(pid);
+                    if id.is_none() {
+                        eprintln!("wait: pid {} is not a child of this shell", pid);
+                    }
+                    id
+                }
+                Err(_) => {
+                    eprintln!("wait: {}: not a pid or valid job spec", target);
+                    None
+                }
+            }
+        };
+        status = match id {
+            Some(id) => job_table::wait_for(id),
+            None => 127,
+        };
+        if signals::interrupted() {
+            return interrupted();
+        }
+    }
+    status
+}
@@ -0,0 +1,122 @@
+use super::super::jobs::{self as job_table, JobFindFail};
+use super::super::signals;
+use std::io::{self, Write};
+
+fn print_usage() {
+    eprintln!(
+        "kill: usage: kill [-s SIGNAL | -n NUM | -SIGNAL] PID | JOBSPEC ... or kill -l [SIGNAL]"
+    );
+}
+
+/* kill -l lists the signals, or converts the names and the numbers */
+fn list_signals(args: &[String]) -> i32 {
+    let mut stdout = io::stdout();
+    if args.is_empty() {
+        for (name, signal) in signals::SIGNALS {
+            if let Err(err) = writeln!(stdout, "{:>2}) SIG{}", signal, name) {
+                return super::write_fail_process(&err);
+            }
+        }
+        return 0;
+    }
+
+    let mut status = 0;
+    for arg in args {
+        let converted = match arg.parse::<i32>() {
+            /* the status of a process killed by a signal */
+            Ok(num) => {
+                signals::signal_name(if num > 128 { num - 128 } else { num }).map(String::from)
+            }
+            Err(_) => signals::find_signal(arg).map(|signal| signal.to_string()),
+        };
+        match converted {
+            Some(converted) => {
+                if let Err(err) = writeln!(stdout, "{}", converted) {
+                    return super::write_fail_process(&err);
+                }
+            }
+            None => {
+                eprintln!("kill: {}: invalid signal specification", arg);
+                status = 1;
+            }
+        }
+    }
+    status
+}
+
+pub fn kill(mut args: Vec<String>) -> i32 {
+    if args.first().map(String::as_str) == Some("-l") {
+        return list_signals(&args[1..]);
+    }
+
+    let mut signal = libc::SIGTERM;
+    if let Some(arg) = args.first().cloned() {
+        let spec = match arg.as_str() {
+            "-s" | "-n" => {
+                args.remove(0);
+                if args.is_empty() {
+                    print_usage();
+                    return 2;
+                }
+                Some(args.remove(0))
+            }
+            "--" => {
+                args.remove(0);
+                None
+            }
+            _ => match arg.strip_prefix('-') {
+                Some(spec) => {
+                    args.remove(0);
+                    Some(spec.to_string())
+                }
+                None => None,
+            },
+        };
+        if let Some(spec) = spec {
+            match signals::find_signal(&spec) {
+                Some(num) => signal = num,
+                None => {
+                    eprintln!("kill: {}: invalid signal specification", spec);
+                    return 1;
+                }
+            }
+        }
+    }
+    if args.is_empty() {
+        print_usage();
+        return 2;
+    }
+
+    let mut status = 0;
+    for target in args {
+        let result = if target.starts_with('%') {
+            match job_table::find_job(&target) {
+                Ok(id) => job_table::signal_job(id, signal),
+                Err(JobFindFail::NoJob) => {
+                    eprintln!("kill: {}: no such job", target);
+                    status = 1;
+                    continue;
+                }
+                Err(JobFindFail::Ambiguous) => {
+                    eprintln!("kill: {}: ambiguous job spec", target);
+                    status = 1;
+                    continue;
+                }
+            }
+        } else {
+            match target.parse::<i32>() {
+                Ok(pid) => job_table::signal_pid(pid, signal),
+                Err(_) => {
+                    eprintln!("kill: {}: arguments must be process or job IDs", target);
+                    status = 1;
+                    continue;
+                }
+            }
+        };
+        if let Err(err) = result {
+            eprintln!("kill: ({}) - {}", target, super::error_reason(&err));
+            status = 1;
+        }
+    }
+    status
+}
@@ -1,7 +1,12 @@
 use super::BUILTIN_CMDS;
+use std::io::{self, Write};
 
-pub fn lsbuiltin() {
+pub fn lsbuiltin() -> i32 {
+    let mut stdout = io::stdout();
     for cmd in BUILTIN_CMDS.iter() {
-        println!("{cmd}");
+        if let Err(err) = writeln!(stdout, "{cmd}") {
+            return super::write_fail_process(&err);
+        }
     }
+    0
 }
@@ -0,0 +1,268 @@
+use super::super::jobs::{self as job_table, Job};
+use super::super::pipline::{Cmds, Pipeline, Stage};
+use super::super::signals;
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, Read, Seek, Write};
+use std::mem::ManuallyDrop;
+use std::os::fd::{FromRawFd, OwnedFd};
+use std::os::unix::ffi::OsStringExt;
+
+/* the outputs are kept in files which are removed at once, so only the shell sees them */
+fn output_file() -> io::Result<File> {
+    let template = env::temp_dir().join("drshell-parallel-XXXXXX");
+    let mut template = CString::new(template.into_os_string().into_vec())?.into_bytes_with_nul();
+    let fd = unsafe { libc::mkostemp(template.as_mut_ptr().cast(), libc::O_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    unsafe { libc::unlink(template.as_ptr().cast()) };
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+/* the stdout and the stderr of a job, printed when the jobs before it are done */
+struct Output {
+    stdout: File,
+    stderr: File,
+}
+
+impl Output {
+    fn new() -> io::Result<Self> {
+        Ok(Self {
+            stdout: output_file()?,
+            stderr: output_file()?,
+        })
+    }
+
+    fn print(mut self) -> io::Result<()> {
+        self.stdout.rewind()?;
+        let mut stdout = io::stdout().lock();
+        io::copy(&mut self.stdout, &mut stdout)?;
+        stdout.flush()?;
+        self.stderr.rewind()?;
+        io::copy(&mut self.stderr, &mut io::stderr())?;
+        Ok(())
+    }
+}
+
+/* the lines of stdin, all of it is read */
+fn read_items() -> io::Result<Vec<String>> {
+    /* the fd 0 is not closed when the file is dropped */
+    let mut stdin = ManuallyDrop::new(unsafe { File::from_raw_fd(0) });
+    let mut input = vec![];
+    stdin.read_to_end(&mut input)?;
+    Ok(String::from_utf8_lossy(&input)
+        .lines()
+        .map(String::from)
+        .collect())
+}
+
+/* {} is replaced by the item, or the item is the last arg */
+fn job_words(template: &[String], item: &str) -> Vec<String> {
+    let mut words: Vec<String> = template
+        .iter()
+        .map(|word| word.replace("{}", item))
+        .collect();
+    if !template.iter().any(|word| word.contains("{}")) {
+        words.push(item.to_string());
+    }
+    words
+}
+
+/* the job's stdout and stderr go to its output files, its stdin is /dev/null */
+fn start_job(words: Vec<String>, output: &Output) -> io::Result<Job> {
+    let command = words.join(" ");
+    let stage = match super::parse_cmd(words) {
+        Ok(cmd) => Stage::new(cmd),
+        Err(reason) => Stage::Shell(Box::new(move || super::parse_cmd_fail_process(reason))),
+    };
+    let fds: Vec<(i32, OwnedFd)> = vec![
+        (0, File::open("/dev/null")?.into()),
+        (1, output.stdout.try_clone()?.into()),
+        (2, output.stderr.try_clone()?.into()),
+    ];
+    let mut cmds = Cmds::new();
+    cmds.add_cmd_with_fds(stage, fds);
+
+    let mut pipeline = Pipeline::new(false);
+    if let Err(err) = pipeline.pipe(cmds) {
+        pipeline.kill();
+        return Err(err);
+    }
+    Ok(pipeline.into_job(command))
+}
+
+fn parse_slots(value: Option<&str>) -> Option<usize> {
+    value?.parse::<usize>().ok().filter(|slots| *slots > 0)
+}
+
+/* Ctrl-C or a failed write stops all the jobs */
+fn kill_all(running: Vec<Job>) {
+    for job in running {
+        job.kill();
+    }
+}
+
+/* parallel [-j N] [-e] CMD [ARGS] [::: ITEMS], the cmd runs once for each item, at most
+ * N at once, and the outputs are printed in the order of the items */
+pub fn parallel(mut args: Vec<String>) -> i32 {
+    let mut slots = std::thread::available_parallelism().map_or(1, usize::from);
+    let mut halt = false;
+
+    while args.first().is_some_and(|arg| arg.starts_with('-')) {
+        let arg = args.remove(0);
+        match arg.as_str() {
+            "-e" => halt = true,
+            _ if arg.starts_with("-j") => {
+                let value = match &arg[2..] {
+                    "" if !args.is_empty() => Some(args.remove(0)),
+                    "" => None,
+                    value => Some(value.to_string()),
+                };
+                match parse_slots(value.as_deref()) {
+                    Some(count) => slots = count,
+                    None => {
+                        eprintln!("parallel: -j: need a number of jobs");
+                        return 2;
+                    }
+                }
+            }
+            _ => {
+                eprintln!("parallel: {}: invalid option", arg);
+                return 2;
+            }
+        }
+    }
+    let items = match args.iter().position(|arg| arg == ":::") {
+        Some(separator) => {
+            let items = args.split_off(separator + 1);
+            args.pop();
+            items
+        }
+        None => match read_items() {
+            Ok(items) => items,
+            Err(err) => {
+                eprintln!("parallel: {}", super::error_reason(&err));
+                return 1;
+            }
+        },
+    };
+    let template = args;
+    if template.is_empty() {
+        eprintln!("parallel: no command to run");
+        return 2;
+    }
+
+    let mut items = items.iter().enumerate();
+    /* the running jobs, and the index, the command and the output of each one */
+    let mut running: Vec<Job> = vec![];
+    let mut running_items: Vec<(usize, String, Output)> = vec![];
+    /* the finished jobs waiting for the ones before them, without an output
+     * when they couldn't start */
+    let mut finished: BTreeMap<usize, (String, i32, Option<Output>)> = BTreeMap::new();
+    let mut next_output = 0;
+    let mut failed = 0;
+    let mut stopping = false;
+
+    loop {
+        if signals::interrupted() {
+            kill_all(running);
+            return 130;
+        }
+        while !stopping && running.len() < slots {
+            let Some((index, item)) = items.next() else {
+                break;
+            };
+            let words = job_words(&template, item);
+            let command = words.join(" ");
+            let started = Output::new().and_then(|output| Ok((start_job(words, &output)?, output)));
+            match started {
+                Ok((job, output)) => {
+                    running.push(job);
+                    running_items.push((index, command, output));
+                }
+                Err(err) => {
+                    eprintln!("parallel: {}: {}", command, super::error_reason(&err));
+                    finished.insert(index, (command, 1, None));
+                    failed += 1;
+                    stopping = halt;
+                }
+            }
+        }
+
+        if !running.is_empty() {
+            let Some(done) = job_table::wait_first(&mut running) else {
+                kill_all(running);
+                eprintln!();
+                return 130;
+            };
+            let status = running.remove(done).status();
+            let (index, command, output) = running_items.remove(done);
+            if status != 0 {
+                failed += 1;
+                stopping = halt;
+            }
+            finished.insert(index, (command, status, Some(output)));
+        }
+
+        /* the outputs are printed as soon as the jobs before them are done */
+        while let Some((command, status, output)) = finished.remove(&next_output) {
+            next_output += 1;
+            let Some(output) = output else {
+                continue;
+            };
+            if let Err(err) = output.print() {
+                kill_all(running);
+                if err.kind() == io::ErrorKind::BrokenPipe {
+                    return 141;
+                }
+                eprintln!("parallel: write error: {}", super::error_reason(&err));
+                return 1;
+            }
+            if status != 0 {
+                eprintln!("parallel: {}: exit status {}", command, status);
+            }
+        }
+        if running.is_empty() && (stopping || items.len() == 0) {
+            break;
+        }
+    }
+
+    /* the number of failed jobs, like GNU parallel */
+    failed.min(101)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(template: &[&str], item: &str) -> Vec<String> {
+        let template: Vec<String> = template.iter().map(|word| word.to_string()).collect();
+        job_words(&template, item)
+    }
+
+    #[test]
+    fn item_replaces_braces() {
+        assert_eq!(
+            words(&["mv", "{}", "{}.bak"], "a b"),
+            ["mv", "a b", "a b.bak"]
+        );
+        assert_eq!(words(&["echo", "x{}y"], ""), ["echo", "xy"]);
+    }
+
+    #[test]
+    fn item_is_last_arg_without_braces() {
+        assert_eq!(words(&["gzip", "-9"], "f.log"), ["gzip", "-9", "f.log"]);
+        assert_eq!(words(&["true"], ""), ["true", ""]);
+    }
+
+    #[test]
+    fn job_slots() {
+        assert_eq!(parse_slots(Some("4")), Some(4));
+        assert_eq!(parse_slots(Some("0")), None);
+        assert_eq!(parse_slots(Some("x")), None);
+        assert_eq!(parse_slots(None), None);
+    }
+}
@@ -1,15 +1,18 @@
 use std::env;
+use std::io::{self, Write};
 
-pub fn pwd() {
+pub fn pwd() -> i32 {
     match env::current_dir() {
-        Ok(path) => {
-            println!("{}", path.display());
-        }
+        Ok(path) => match writeln!(io::stdout(), "{}", path.display()) {
+            Ok(()) => 0,
+            Err(err) => super::write_fail_process(&err),
+        },
         Err(err) => {
             eprintln!(
                 "{}",
                 "can't get the current work directory: ".to_string() + &err.to_string()
             );
+            1
         }
     }
 }
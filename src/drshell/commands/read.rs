@@ -0,0 +1,434 @@
+use super::super::expand;
+use super::super::signals;
+use super::super::vars;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+struct ReadOpts {
+    /* -r, the backslashes are kept */
+    raw: bool,
+    prompt: Option<String>,
+    /* -s, the input is not echoed */
+    silent: bool,
+    timeout: Option<Duration>,
+    /* -n, the count of chars */
+    count: Option<usize>,
+    delim: u8,
+    array: Option<String>,
+    /* -e, the line is edited with rustyline */
+    edit: bool,
+    fd: i32,
+}
+
+enum ReadFail {
+    Eof,
+    Timeout,
+    Interrupted,
+    Error(io::Error),
+}
+
+/* the input bytes, with whether each one was escaped by a backslash */
+type Input = Vec<(u8, bool)>;
+
+/* the terminal modes changed for -s and -n, restored when dropped */
+struct TermModes {
+    fd: i32,
+    saved: Option<libc::termios>,
+}
+
+impl TermModes {
+    fn set(fd: i32, silent: bool, by_char: bool) -> Self {
+        let mut modes = Self { fd, saved: None };
+        let mut tmodes: libc::termios = unsafe { std::mem::zeroed() };
+        if !(silent || by_char) || unsafe { libc::tcgetattr(fd, &mut tmodes) } < 0 {
+            return modes;
+        }
+        modes.saved = Some(tmodes);
+        if silent {
+            tmodes.c_lflag &= !(libc::ECHO | libc::ECHONL);
+        }
+        if by_char {
+            tmodes.c_lflag &= !libc::ICANON;
+            tmodes.c_cc[libc::VMIN] = 1;
+            tmodes.c_cc[libc::VTIME] = 0;
+        }
+        unsafe { libc::tcsetattr(fd, libc::TCSADRAIN, &tmodes) };
+        modes
+    }
+}
+
+impl Drop for TermModes {
+    fn drop(&mut self) {
+        if let Some(tmodes) = self.saved {
+            unsafe { libc::tcsetattr(self.fd, libc::TCSADRAIN, &tmodes) };
+        }
+    }
+}
+
+/* the length of a utf-8 char from its first byte */
+fn char_len(byte: u8) -> usize {
+    match byte {
+        0xf0.. => 4,
+        0xe0.. => 3,
+        0xc0.. => 2,
+        _ => 1,
+    }
+}
+
+/* wait until the fd can be read, or until the deadline */
+fn wait_input(fd: i32, deadline: Option<Instant>) -> Result<(), ReadFail> {
+    let Some(deadline) = deadline else {
+        return Ok(());
+    };
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        let millis = remaining.as_millis().min(i32::MAX as u128) as i32;
+        match unsafe { libc::poll(&mut pollfd, 1, millis) } {
+            0 => return Err(ReadFail::Timeout),
+            ready if ready > 0 => return Ok(()),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(ReadFail::Error(err));
+                }
+                if signals::interrupted() {
+                    return Err(ReadFail::Interrupted);
+                }
+            }
+        }
+    }
+}
+
+/* one byte at a time, so the rest of the input is left for the next commands */
+fn read_byte(fd: i32, deadline: Option<Instant>) -> Result<u8, ReadFail> {
+    loop {
+        wait_input(fd, deadline)?;
+        let mut byte = 0_u8;
+        match unsafe { libc::read(fd, (&mut byte as *mut u8).cast(), 1) } {
+            0 => return Err(ReadFail::Eof),
+            1 => return Ok(byte),
+            _ => {
+                let err = io::Error::last_os_error();
+                if err.kind() != io::ErrorKind::Interrupted {
+                    return Err(ReadFail::Error(err));
+                }
+                if signals::interrupted() {
+                    return Err(ReadFail::Interrupted);
+                }
+            }
+        }
+    }
+}
+
+/* read until the delimiter or the count of chars, without -r a backslash escapes
+ * the next byte and a backslash before a new line joins the lines */
+fn read_input(opts: &ReadOpts, input: &mut Input) -> Result<(), ReadFail> {
+    let deadline = opts.timeout.map(|timeout| Instant::now() + timeout);
+    let mut chars = 0;
+
+    while opts.count.map_or(true, |count| chars < count) {
+        let mut byte = read_byte(opts.fd, deadline)?;
+        let mut escaped = false;
+        if byte == b'\\' && !opts.raw {
+            byte = read_byte(opts.fd, deadline)?;
+            if byte == b'\n' {
+                continue;
+            }
+            escaped = true;
+        }
+        if byte == opts.delim && !escaped {
+            break;
+        }
+        input.push((byte, escaped));
+        for _ in 1..char_len(byte) {
+            let byte = read_byte(opts.fd, deadline)?;
+            input.push((byte, escaped));
+        }
+        chars += 1;
+    }
+    Ok(())
+}
+
+/* the line of rustyline, with the backslashes of the line processed like the read ones */
+fn edit_input(opts: &ReadOpts, input: &mut Input) -> Result<(), ReadFail> {
+    /* the editor may change the handler of Ctrl-C */
+    let mut sigint: libc::sigaction = unsafe { std::mem::zeroed() };
+    unsafe { libc::sigaction(libc::SIGINT, std::ptr::null(), &mut sigint) };
+    let line = DefaultEditor::new()
+        .and_then(|mut editor| editor.readline(opts.prompt.as_deref().unwrap_or("")));
+    unsafe { libc::sigaction(libc::SIGINT, &sigint, std::ptr::null_mut()) };
+
+    let line = match line {
+        Ok(line) => line,
+        Err(ReadlineError::Eof) => return Err(ReadFail::Eof),
+        Err(ReadlineError::Interrupted) => {
+            signals::interrupt();
+            return Err(ReadFail::Interrupted);
+        }
+        Err(ReadlineError::Io(err)) => return Err(ReadFail::Error(err)),
+        Err(err) => return Err(ReadFail::Error(io::Error::other(err.to_string()))),
+    };
+    let mut bytes = line.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte == b'\\' && !opts.raw {
+            if let Some(byte) = bytes.next() {
+                input.push((byte, true));
+            }
+            continue;
+        }
+        input.push((byte, false));
+    }
+    Ok(())
+}
+
+/* split by ifs, the value of $IFS, into at most max fields, the last one keeps the rest
+ * of the input: the spaces, tabs and new lines of IFS are trimmed and several of them are
+ * one separator, each other char of IFS is a separator */
+fn split_fields(input: &[(u8, bool)], max: usize, ifs: &str) -> Vec<String> {
+    let separator = |(byte, escaped): &(u8, bool)| !escaped && ifs.as_bytes().contains(byte);
+    let blank = |item: &(u8, bool)| separator(item) && b" \t\n".contains(&item.0);
+    let text = |bytes: &[(u8, bool)]| {
+        let bytes: Vec<u8> = bytes.iter().map(|(byte, _)| *byte).collect();
+        String::from_utf8_lossy(&bytes).into_owned()
+    };
+
+    let mut fields = vec![];
+    let mut pos = input.iter().take_while(|item| blank(item)).count();
+    while pos < input.len() {
+        if fields.len() + 1 == max {
+            let rest = &input[pos..];
+            let end = rest.len() - rest.iter().rev().take_while(|item| blank(item)).count();
+            fields.push(text(&rest[..end]));
+            break;
+        }
+        let len = input[pos..]
+            .iter()
+            .take_while(|item| !separator(item))
+            .count();
+        fields.push(text(&input[pos..pos + len]));
+        pos += len;
+        pos += input[pos..].iter().take_while(|item| blank(item)).count();
+        if input
+            .get(pos)
+            .is_some_and(|item| separator(item) && !blank(item))
+        {
+            pos += 1;
+            pos += input[pos..].iter().take_while(|item| blank(item)).count();
+        }
+    }
+    fields
+}
+
+/* the input goes to REPLY as it is, to the names split by $IFS, or to the array */
+fn assign(opts: &ReadOpts, names: &[String], input: &Input) {
+    if let Some(array) = &opts.array {
+        vars::set_array(array, split_fields(input, usize::MAX, &expand::ifs()));
+        return;
+    }
+    if names.is_empty() {
+        let bytes: Vec<u8> = input.iter().map(|(byte, _)| *byte).collect();
+        vars::set_var("REPLY", &String::from_utf8_lossy(&bytes));
+        return;
+    }
+    let mut fields = split_fields(input, names.len(), &expand::ifs()).into_iter();
+    for name in names {
+        vars::set_var(name, &fields.next().unwrap_or_default());
+    }
+}
+
+/* the value of an option, in the same arg or in the next one */
+fn option_value(
+    option: char,
+    rest: &str,
+    args: &mut impl Iterator<Item = String>,
+) -> Result<String, i32> {
+    if !rest.is_empty() {
+        return Ok(rest.to_string());
+    }
+    args.next().ok_or_else(|| {
+        eprintln!("read: -{}: option requires an argument", option);
+        2
+    })
+}
+
+fn parse_opts(args: Vec<String>) -> Result<(ReadOpts, Vec<String>), i32> {
+    let mut opts = ReadOpts {
+        raw: false,
+        prompt: None,
+        silent: false,
+        timeout: None,
+        count: None,
+        delim: b'\n',
+        array: None,
+        edit: false,
+        fd: 0,
+    };
+    let mut args = args.into_iter();
+    let mut names = vec![];
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            names.extend(args.by_ref());
+            break;
+        }
+        let Some(options) = arg.strip_prefix('-').filter(|options| !options.is_empty()) else {
+            names.push(arg);
+            continue;
+        };
+        for (i, option) in options.char_indices() {
+            let rest = &options[i + option.len_utf8()..];
+            match option {
+                'r' => opts.raw = true,
+                's' => opts.silent = true,
+                'e' => opts.edit = true,
+                'p' => opts.prompt = Some(option_value(option, rest, &mut args)?),
+                'd' => {
+                    /* -d '' reads until a NUL byte */
+                    let delim = option_value(option, rest, &mut args)?;
+                    opts.delim = delim.bytes().next().unwrap_or(0);
+                }
+                'a' => opts.array = Some(option_value(option, rest, &mut args)?),
+                't' => {
+                    let value = option_value(option, rest, &mut args)?;
+                    match value.parse::<f64>().ok().filter(|secs| *secs >= 0.0) {
+                        Some(secs) => opts.timeout = Some(Duration::from_secs_f64(secs)),
+                        None => {
+                            eprintln!("read: {}: invalid timeout specification", value);
+                            return Err(1);
+                        }
+                    }
+                }
+                'n' => {
+                    let value = option_value(option, rest, &mut args)?;
+                    match value.parse::<usize>() {
+                        Ok(count) => opts.count = Some(count),
+                        Err(_) => {
+                            eprintln!("read: {}: invalid number", value);
+                            return Err(1);
+                        }
+                    }
+                }
+                'u' => {
+                    let value = option_value(option, rest, &mut args)?;
+                    match value.parse::<i32>() {
+                        Ok(fd) if unsafe { libc::fcntl(fd, libc::F_GETFD) } >= 0 => opts.fd = fd,
+                        _ => {
+                            eprintln!("read: {}: invalid file descriptor", value);
+                            return Err(1);
+                        }
+                    }
+                }
+                _ => {
+                    eprintln!("read: -{}: invalid option", option);
+                    return Err(2);
+                }
+            }
+            if "pdatnu".contains(option) {
+                break;
+            }
+        }
+    }
+
+    let invalid = names
+        .iter()
+        .chain(opts.array.iter())
+        .find(|name| !vars::valid_name(name));
+    if let Some(name) = invalid {
+        eprintln!("read: `{}': not a valid identifier", name);
+        return Err(1);
+    }
+    Ok((opts, names))
+}
+
+/* the status is 1 at the end of the input and 142 after the timeout, the input read
+ * until then is assigned anyway */
+pub fn read(args: Vec<String>) -> i32 {
+    let (opts, names) = match parse_opts(args) {
+        Ok(parsed) => parsed,
+        Err(status) => return status,
+    };
+
+    /* -t 0 only tells whether there is input */
+    if opts.timeout.is_some_and(|timeout| timeout.is_zero()) {
+        return match wait_input(opts.fd, Some(Instant::now())) {
+            Ok(()) => 0,
+            Err(_) => 1,
+        };
+    }
+
+    let terminal = unsafe { libc::isatty(opts.fd) } == 1;
+    let mut input = vec![];
+    let result = if opts.edit && terminal && opts.fd == 0 {
+        edit_input(&opts, &mut input)
+    } else {
+        if let (Some(prompt), true) = (&opts.prompt, terminal) {
+            eprint!("{}", prompt);
+            let _ = io::stderr().flush();
+        }
+        let _modes = if terminal {
+            Some(TermModes::set(opts.fd, opts.silent, opts.count.is_some()))
+        } else {
+            None
+        };
+        read_input(&opts, &mut input)
+    };
+
+    let status = match result {
+        Ok(()) => 0,
+        Err(ReadFail::Eof) => 1,
+        Err(ReadFail::Timeout) => 128 + libc::SIGALRM,
+        Err(ReadFail::Interrupted) => return 130,
+        Err(ReadFail::Error(err)) => {
+            eprintln!("read: read error: {}", super::error_reason(&err));
+            return 1;
+        }
+    };
+    assign(&opts, &names, &input);
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /* a backslash escapes the next byte, like without -r */
+    fn split(text: &str, max: usize, ifs: &str) -> Vec<String> {
+        let mut input: Input = vec![];
+        let mut bytes = text.bytes();
+        while let Some(byte) = bytes.next() {
+            match byte {
+                b'\\' => input.extend(bytes.next().map(|byte| (byte, true))),
+                _ => input.push((byte, false)),
+            }
+        }
+        split_fields(&input, max, ifs)
+    }
+
+    #[test]
+    fn whitespace_is_trimmed() {
+        assert_eq!(split("  a  b   c d  ", 3, " \t\n"), ["a", "b", "c d"]);
+        assert_eq!(split("\ta b\t", usize::MAX, " \t\n"), ["a", "b"]);
+        assert!(split("   ", 2, " \t\n").is_empty());
+    }
+
+    #[test]
+    fn other_chars_separate_each_field() {
+        assert_eq!(split("x::y", 3, ":"), ["x", "", "y"]);
+        assert_eq!(split("x:y:z", 2, ":"), ["x", "y:z"]);
+        assert_eq!(split("a : b", usize::MAX, " :"), ["a", "b"]);
+        assert_eq!(split(":a", usize::MAX, ":"), ["", "a"]);
+    }
+
+    #[test]
+    fn escaped_and_empty_ifs() {
+        assert_eq!(split("a\\ b c", usize::MAX, " "), ["a b", "c"]);
+        assert_eq!(split("a b", usize::MAX, ""), ["a b"]);
+    }
+}
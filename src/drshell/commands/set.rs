@@ -0,0 +1,83 @@
+use super::super::expand;
+use super::super::options::{self, ShellOpt};
+use super::super::vars;
+use std::io::{self, Write};
+
+fn print_opts(reusable: bool) -> io::Result<()> {
+    let mut stdout = io::stdout();
+    for (name, on) in options::list_opts() {
+        if reusable {
+            writeln!(stdout, "set {}o {}", if on { '-' } else { '+' }, name)?;
+        } else {
+            writeln!(stdout, "{:<15} {}", name, if on { "on" } else { "off" })?;
+        }
+    }
+    Ok(())
+}
+
+fn print_vars() -> io::Result<()> {
+    let mut stdout = io::stdout();
+    for (name, value) in vars::all_vars() {
+        writeln!(stdout, "{}={}", name, expand::quote(&value))?;
+    }
+    Ok(())
+}
+
+pub fn set(args: Vec<String>) -> i32 {
+    if args.is_empty() {
+        return match print_vars() {
+            Ok(()) => 0,
+            Err(err) => super::write_fail_process(&err),
+        };
+    }
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let on = arg.starts_with('-');
+        if arg == "--" {
+            vars::set_positional_params(args.collect());
+            return 0;
+        } else if arg == "-" {
+            /* like --, but turns off -x and -v and keeps the params without arguments */
+            options::set_opt(ShellOpt::Xtrace, false);
+            options::set_opt(ShellOpt::Verbose, false);
+            let params: Vec<String> = args.collect();
+            if !params.is_empty() {
+                vars::set_positional_params(params);
+            }
+            return 0;
+        } else if arg == "-o" || arg == "+o" {
+            let Some(name) = args.next() else {
+                return match print_opts(!on) {
+                    Ok(()) => 0,
+                    Err(err) => super::write_fail_process(&err),
+                };
+            };
+            match options::find_by_name(&name) {
+                Some(opt) => options::set_opt(opt, on),
+                None => {
+                    eprintln!("set: {}: invalid option name", name);
+                    return 1;
+                }
+            }
+        } else if arg.len() > 1 && (on || arg.starts_with('+')) {
+            for letter in arg.chars().skip(1) {
+                match options::find_by_letter(letter) {
+                    Some(opt) => options::set_opt(opt, on),
+                    None => {
+                        eprintln!("set: {}{}: invalid option", &arg[..1], letter);
+                        return 2;
+                    }
+                }
+            }
+        } else {
+            /* the rest are the positional params */
+            let mut params = vec![arg];
+            params.extend(args);
+            vars::set_positional_params(params);
+            return 0;
+        }
+    }
+
+    0
+}
@@ -0,0 +1,32 @@
+use super::super::env as drshell_env;
+use super::super::script;
+use super::super::vars;
+use std::path::PathBuf;
+
+pub fn source(mut args: Vec<String>) -> i32 {
+    let file = args.remove(0);
+    let path = if file.contains('/') {
+        PathBuf::from(&file)
+    } else {
+        drshell_env::find_file_in_path(&file).unwrap_or(PathBuf::from(&file))
+    };
+
+    /* the args replace the positional params while the file runs */
+    let old_params = if args.is_empty() {
+        None
+    } else {
+        Some(vars::set_positional_params(args))
+    };
+    let status = match script::run_file(&path) {
+        Ok(status) => status,
+        Err(err) => {
+            eprintln!("source: {}: {}", file, super::error_reason(&err));
+            1
+        }
+    };
+    if let Some(old_params) = old_params {
+        vars::set_positional_params(old_params);
+    }
+
+    status
+}
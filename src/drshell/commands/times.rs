@@ -0,0 +1,15 @@
+use super::super::times;
+use std::io::{self, Write};
+use std::time::Duration;
+
+/* 0m0.010s 0m0.002s for the shell, then for its children */
+pub fn times() -> i32 {
+    let mut stdout = io::stdout();
+    for cpu in [times::shell_times(), times::children_times()] {
+        let line = times::format_times("%3lU %3lS", Duration::ZERO, cpu);
+        if let Err(err) = writeln!(stdout, "{}", line) {
+            return super::write_fail_process(&err);
+        }
+    }
+    0
+}
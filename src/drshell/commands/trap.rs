@@ -0,0 +1,203 @@
+use super::super::expand;
+use super::super::options;
+use super::super::script;
+use super::super::signals;
+use super::super::vars;
+use lazy_static::lazy_static;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Trap {
+    Exit,
+    Err,
+    Signal(i32),
+}
+
+lazy_static! {
+    static ref TRAPS: Mutex<BTreeMap<Trap, String>> = Mutex::new(BTreeMap::new());
+}
+
+/* the commands of the ERR trap don't run it again */
+static IN_ERR_TRAP: AtomicBool = AtomicBool::new(false);
+
+fn find_trap(name: &str) -> Option<Trap> {
+    match name.to_ascii_uppercase().as_str() {
+        "EXIT" | "0" => Some(Trap::Exit),
+        "ERR" => Some(Trap::Err),
+        _ => signals::find_signal(name).map(Trap::Signal),
+    }
+}
+
+fn trap_name(trap: Trap) -> String {
+    match trap {
+        Trap::Exit => "EXIT".to_string(),
+        Trap::Err => "ERR".to_string(),
+        Trap::Signal(signal) => "SIG".to_string() + signals::signal_name(signal).unwrap_or("?"),
+    }
+}
+
+fn print_trap(trap: Trap, action: &str) -> io::Result<()> {
+    writeln!(
+        io::stdout(),
+        "trap -- {} {}",
+        expand::quote(action),
+        trap_name(trap)
+    )
+}
+
+fn set_trap(trap: Trap, action: Option<String>) -> bool {
+    if let Trap::Signal(signal) = trap {
+        let set = match action.as_deref() {
+            /* the interactive shell keeps handling Ctrl-C, the hangup and SIGTERM */
+            None if signal == libc::SIGINT && options::interactive() => signals::catch_interrupt(),
+            None if [libc::SIGHUP, libc::SIGTERM].contains(&signal) && options::interactive() => {
+                signals::catch(signal)
+            }
+            None => signals::set_default(signal),
+            Some("") => signals::ignore(signal),
+            Some(_) => signals::catch(signal),
+        };
+        if !set {
+            return false;
+        }
+    }
+    let mut traps = TRAPS.lock().unwrap();
+    match action {
+        Some(action) => traps.insert(trap, action),
+        None => traps.remove(&trap),
+    };
+    true
+}
+
+pub fn trap(mut args: Vec<String>) -> i32 {
+    let mut status = 0;
+
+    if args.first().map(String::as_str) == Some("-l") {
+        let mut stdout = io::stdout();
+        for (name, signal) in signals::SIGNALS {
+            if let Err(err) = writeln!(stdout, "{:>2}) SIG{}", signal, name) {
+                return super::write_fail_process(&err);
+            }
+        }
+        return 0;
+    }
+    if args.is_empty() || args[0] == "-p" {
+        let traps = TRAPS.lock().unwrap();
+        if args.len() <= 1 {
+            for (trap, action) in traps.iter() {
+                if let Err(err) = print_trap(*trap, action) {
+                    return super::write_fail_process(&err);
+                }
+            }
+            return 0;
+        }
+        for name in &args[1..] {
+            match find_trap(name) {
+                Some(trap) => {
+                    let Some(action) = traps.get(&trap) else {
+                        continue;
+                    };
+                    if let Err(err) = print_trap(trap, action) {
+                        return super::write_fail_process(&err);
+                    }
+                }
+                None => {
+                    eprintln!("trap: {}: invalid signal specification", name);
+                    status = 1;
+                }
+            }
+        }
+        return status;
+    }
+
+    /* trap - SIG, or trap SIG, resets the signals */
+    let action = if args[0] == "-" || (args.len() == 1 && find_trap(&args[0]).is_some()) {
+        if args[0] == "-" {
+            args.remove(0);
+        }
+        None
+    } else {
+        Some(args.remove(0))
+    };
+    for name in args {
+        let trap = match find_trap(&name) {
+            Some(trap) => trap,
+            None => {
+                eprintln!("trap: {}: invalid signal specification", name);
+                status = 1;
+                continue;
+            }
+        };
+        if !set_trap(trap, action.clone()) {
+            eprintln!("trap: {}: can't trap the signal", name);
+            status = 1;
+        }
+    }
+
+    status
+}
+
+/* the forked shells keep only the ignored signals */
+pub fn reset_traps() {
+    let mut traps = TRAPS.lock().unwrap();
+    for (trap, action) in traps.iter() {
+        if let (Trap::Signal(signal), false) = (trap, action.is_empty()) {
+            signals::set_default(*signal);
+        }
+    }
+    traps.retain(|_, action| action.is_empty());
+}
+
+/* trap '' SIG */
+pub fn ignored(signal: i32) -> bool {
+    TRAPS
+        .lock()
+        .unwrap()
+        .get(&Trap::Signal(signal))
+        .is_some_and(|action| action.is_empty())
+}
+
+/* the status is kept while the actions run */
+fn run_action(action: &str) {
+    if action.is_empty() {
+        return;
+    }
+    let status = vars::status();
+    script::run_line(action);
+    vars::set_status(status);
+}
+
+pub fn run_pending_traps() {
+    for signal in signals::take_pending() {
+        let action = TRAPS.lock().unwrap().get(&Trap::Signal(signal)).cloned();
+        match action {
+            Some(action) => run_action(&action),
+            None if signal == libc::SIGHUP => super::hangup(),
+            /* the interactive shell exits with its exit trap and the history saved */
+            None if signal == libc::SIGTERM => super::exit_shell(128 + libc::SIGTERM),
+            None => {}
+        }
+    }
+}
+
+pub fn run_err_trap() {
+    let action = TRAPS.lock().unwrap().get(&Trap::Err).cloned();
+    if let Some(action) = action {
+        if IN_ERR_TRAP.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        run_action(&action);
+        IN_ERR_TRAP.store(false, Ordering::SeqCst);
+    }
+}
+
+/* the exit trap runs only once */
+pub fn run_exit_trap() {
+    let action = TRAPS.lock().unwrap().remove(&Trap::Exit);
+    if let Some(action) = action {
+        run_action(&action);
+    }
+}
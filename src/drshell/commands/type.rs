@@ -1,20 +1,33 @@
 use super::super::env as drshell_env;
 use super::BUILTIN_CMDS;
+use std::io::{self, Write};
 
-pub fn r#type(command: String) {
-    for cmd in BUILTIN_CMDS.iter() {
-        if &command.as_str() == cmd {
-            println!("{}", command + " is a shell builtin");
-            return;
-        }
+/* the description of what the command is, None when it is not found */
+fn describe(command: &str) -> Option<String> {
+    if let Some(value) = super::alias::find_alias(command) {
+        return Some(format!("{} is aliased to `{}'", command, value));
+    }
+    if ["time", "!", "coproc"].contains(&command) {
+        return Some(format!("{} is a shell keyword", command));
+    }
+    if BUILTIN_CMDS.contains(&command) {
+        return Some(format!("{} is a shell builtin", command));
     }
     if command.is_empty() {
-        eprintln!("{}", command + ": not found");
-        return;
+        return None;
     }
-    if let Some(path) = drshell_env::if_executable(&command) {
-        println!("{}", command + " is " + &path.display().to_string());
-    } else {
-        eprintln!("{}", command + ": not found");
+    drshell_env::if_executable(command).map(|path| format!("{} is {}", command, path.display()))
+}
+
+pub fn r#type(command: String) -> i32 {
+    match describe(&command) {
+        Some(description) => match writeln!(io::stdout(), "{}", description) {
+            Ok(()) => 0,
+            Err(err) => super::write_fail_process(&err),
+        },
+        None => {
+            eprintln!("{}", command + ": not found");
+            1
+        }
     }
 }
@@ -0,0 +1,199 @@
+use std::io::{self, Write};
+
+struct Limit {
+    option: char,
+    name: &'static str,
+    unit: &'static str,
+    resource: i32,
+    /* the bytes or the seconds of one unit */
+    scale: libc::rlim_t,
+}
+
+/* the order of ulimit -a */
+const LIMITS: [Limit; 6] = [
+    Limit {
+        option: 'c',
+        name: "core file size",
+        unit: "blocks",
+        resource: libc::RLIMIT_CORE as i32,
+        scale: 1024,
+    },
+    Limit {
+        option: 'f',
+        name: "file size",
+        unit: "blocks",
+        resource: libc::RLIMIT_FSIZE as i32,
+        scale: 1024,
+    },
+    Limit {
+        option: 'n',
+        name: "open files",
+        unit: "",
+        resource: libc::RLIMIT_NOFILE as i32,
+        scale: 1,
+    },
+    Limit {
+        option: 's',
+        name: "stack size",
+        unit: "kbytes",
+        resource: libc::RLIMIT_STACK as i32,
+        scale: 1024,
+    },
+    Limit {
+        option: 't',
+        name: "cpu time",
+        unit: "seconds",
+        resource: libc::RLIMIT_CPU as i32,
+        scale: 1,
+    },
+    Limit {
+        option: 'v',
+        name: "virtual memory",
+        unit: "kbytes",
+        resource: libc::RLIMIT_AS as i32,
+        scale: 1024,
+    },
+];
+
+fn get_limit(limit: &Limit) -> io::Result<libc::rlimit> {
+    let mut rlimit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    if unsafe { libc::getrlimit(limit.resource as _, &mut rlimit) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(rlimit)
+}
+
+fn limit_text(value: libc::rlim_t, limit: &Limit) -> String {
+    if value == libc::RLIM_INFINITY {
+        "unlimited".to_string()
+    } else {
+        (value / limit.scale).to_string()
+    }
+}
+
+/* a number of units, unlimited, or the current soft or hard limit */
+fn parse_value(value: &str, limit: &Limit, rlimit: &libc::rlimit) -> Option<libc::rlim_t> {
+    match value {
+        "unlimited" => Some(libc::RLIM_INFINITY),
+        "soft" => Some(rlimit.rlim_cur),
+        "hard" => Some(rlimit.rlim_max),
+        _ => value.parse::<libc::rlim_t>().ok()?.checked_mul(limit.scale),
+    }
+}
+
+/* without -S or -H, both limits are set and the soft one is printed */
+fn set_limit(limit: &Limit, value: &str, soft: bool, hard: bool) -> i32 {
+    let mut rlimit = match get_limit(limit) {
+        Ok(rlimit) => rlimit,
+        Err(err) => {
+            eprintln!("ulimit: {}: {}", limit.name, super::error_reason(&err));
+            return 1;
+        }
+    };
+    let Some(value) = parse_value(value, limit, &rlimit) else {
+        eprintln!("ulimit: {}: invalid number", value);
+        return 1;
+    };
+    if soft || !hard {
+        rlimit.rlim_cur = value;
+    }
+    if hard || !soft {
+        rlimit.rlim_max = value;
+    }
+    if unsafe { libc::setrlimit(limit.resource as _, &rlimit) } < 0 {
+        let err = io::Error::last_os_error();
+        eprintln!(
+            "ulimit: {}: cannot modify limit: {}",
+            limit.name,
+            super::error_reason(&err)
+        );
+        return 1;
+    }
+    0
+}
+
+fn print_limit(limit: &Limit, hard: bool, long: bool) -> i32 {
+    let rlimit = match get_limit(limit) {
+        Ok(rlimit) => rlimit,
+        Err(err) => {
+            eprintln!("ulimit: {}: {}", limit.name, super::error_reason(&err));
+            return 1;
+        }
+    };
+    let value = limit_text(
+        if hard {
+            rlimit.rlim_max
+        } else {
+            rlimit.rlim_cur
+        },
+        limit,
+    );
+    let line = if long {
+        let unit = if limit.unit.is_empty() {
+            format!("(-{})", limit.option)
+        } else {
+            format!("({}, -{})", limit.unit, limit.option)
+        };
+        format!("{:<20}{:>16} {}", limit.name, unit, value)
+    } else {
+        value
+    };
+    match writeln!(io::stdout(), "{}", line) {
+        Ok(()) => 0,
+        Err(err) => super::write_fail_process(&err),
+    }
+}
+
+pub fn ulimit(args: Vec<String>) -> i32 {
+    let mut soft = false;
+    let mut hard = false;
+    let mut all = false;
+    let mut limits = vec![];
+    let mut value = None;
+
+    for arg in args {
+        let Some(options) = arg.strip_prefix('-').filter(|options| !options.is_empty()) else {
+            value = Some(arg);
+            continue;
+        };
+        for option in options.chars() {
+            match option {
+                'S' => soft = true,
+                'H' => hard = true,
+                'a' => all = true,
+                _ => match LIMITS.iter().find(|limit| limit.option == option) {
+                    Some(limit) => limits.push(limit),
+                    None => {
+                        eprintln!("ulimit: -{}: invalid option", option);
+                        return 2;
+                    }
+                },
+            }
+        }
+    }
+
+    if all {
+        limits = LIMITS.iter().collect();
+    } else if limits.is_empty() {
+        /* the file size by default */
+        limits.push(&LIMITS[1]);
+    }
+    let mut status = 0;
+    match value {
+        Some(value) if !all => {
+            for limit in limits {
+                status |= set_limit(limit, &value, soft, hard);
+            }
+        }
+        _ => {
+            let long = limits.len() > 1;
+            for limit in limits {
+                status |= print_limit(limit, hard && !soft, long);
+            }
+        }
+    }
+    status
+}
@@ -0,0 +1,168 @@
+use std::io::{self, Write};
+
+/* the mask can only be read by setting it */
+fn get_mask() -> u32 {
+    unsafe {
+        let mask = libc::umask(0);
+        libc::umask(mask);
+        mask as u32
+    }
+}
+
+/* u=rwx,g=rx,o=rx, the permissions which are not masked */
+fn symbolic(mask: u32) -> String {
+    let allowed = !mask & 0o777;
+    let clauses: Vec<String> = [('u', 6), ('g', 3), ('o', 0)]
+        .iter()
+        .map(|(who, shift)| {
+            let bits = allowed >> shift;
+            let mut clause = format!("{}=", who);
+            for (bit, perm) in [(4, 'r'), (2, 'w'), (1, 'x')] {
+                if bits & bit != 0 {
+                    clause.push(perm);
+                }
+            }
+            clause
+        })
+        .collect();
+    clauses.join(",")
+}
+
+/* [ugoa]*[+-=][rwx]* separated by commas, changing the current mask */
+fn parse_symbolic(mode: &str, mask: u32) -> Result<u32, String> {
+    let mut allowed = !mask & 0o777;
+
+    for clause in mode.split(',') {
+        let mut chars = clause.chars().peekable();
+        let mut who = 0;
+        while let Some(c) = chars.next_if(|c| "ugoa".contains(*c)) {
+            who |= match c {
+                'u' => 0o700,
+                'g' => 0o070,
+                'o' => 0o007,
+                _ => 0o777,
+            };
+        }
+        if who == 0 {
+            who = 0o777;
+        }
+        let Some(op) = chars.next_if(|c| "+-=".contains(*c)) else {
+            let c = chars.next().map(String::from).unwrap_or_default();
+            return Err(format!("`{}': invalid symbolic mode operator", c));
+        };
+        let mut perms = 0;
+        for c in chars {
+            perms |= match c {
+                'r' => 0o444,
+                'w' => 0o222,
+                'x' => 0o111,
+                _ => return Err(format!("`{}': invalid symbolic mode character", c)),
+            };
+        }
+        match op {
+            '+' => allowed |= who & perms,
+            '-' => allowed &= !(who & perms),
+            _ => allowed = (allowed & !who) | (who & perms),
+        }
+    }
+
+    Ok(!allowed & 0o777)
+}
+
+pub fn umask(args: Vec<String>) -> i32 {
+    let mut symbolic_output = false;
+    let mut reusable = false;
+    let mut mode = None;
+
+    for arg in args {
+        match arg.as_str() {
+            "-S" => symbolic_output = true,
+            "-p" => reusable = true,
+            _ => mode = Some(arg),
+        }
+    }
+
+    let mask = get_mask();
+    let Some(mode) = mode else {
+        let text = if symbolic_output {
+            symbolic(mask)
+        } else {
+            format!("{:04o}", mask)
+        };
+        let line = if reusable {
+            let option = if symbolic_output { "-S " } else { "" };
+            format!("umask {}{}", option, text)
+        } else {
+            text
+        };
+        return match writeln!(io::stdout(), "{}", line) {
+            Ok(()) => 0,
+            Err(err) => super::write_fail_process(&err),
+        };
+    };
+
+    let new_mask = if mode.starts_with(|c: char| c.is_ascii_digit()) {
+        match u32::from_str_radix(&mode, 8) {
+            Ok(new_mask) if new_mask <= 0o777 => new_mask,
+            _ => {
+                eprintln!("umask: {}: octal number out of range", mode);
+                return 1;
+            }
+        }
+    } else {
+        match parse_symbolic(&mode, mask) {
+            Ok(new_mask) => new_mask,
+            Err(reason) => {
+                eprintln!("umask: {}", reason);
+                return 1;
+            }
+        }
+    };
+    unsafe { libc::umask(new_mask as libc::mode_t) };
+    if symbolic_output {
+        if let Err(err) = writeln!(io::stdout(), "{}", symbolic(new_mask)) {
+            return super::write_fail_process(&err);
+        }
+    }
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbolic_modes() {
+        assert_eq!(parse_symbolic("u=rwx,g=rx,o=rx", 0o077), Ok(0o022));
+        assert_eq!(parse_symbolic("g+w", 0o022), Ok(0o002));
+        assert_eq!(parse_symbolic("o-rwx", 0o022), Ok(0o027));
+        assert_eq!(parse_symbolic("ug+w,o=", 0o022), Ok(0o007));
+        /* without who, all of them */
+        assert_eq!(parse_symbolic("=r", 0), Ok(0o333));
+        assert_eq!(parse_symbolic("a+x", 0o777), Ok(0o666));
+        assert_eq!(parse_symbolic("u=", 0o022), Ok(0o722));
+    }
+
+    #[test]
+    fn invalid_symbolic_modes() {
+        assert_eq!(
+            parse_symbolic("u*r", 0),
+            Err("`*': invalid symbolic mode operator".to_string())
+        );
+        assert_eq!(
+            parse_symbolic("u", 0),
+            Err("`': invalid symbolic mode operator".to_string())
+        );
+        assert_eq!(
+            parse_symbolic("g+rz", 0),
+            Err("`z': invalid symbolic mode character".to_string())
+        );
+    }
+
+    #[test]
+    fn symbolic_output() {
+        assert_eq!(symbolic(0o022), "u=rwx,g=rx,o=rx");
+        assert_eq!(symbolic(0o077), "u=rwx,g=,o=");
+        assert_eq!(symbolic(0o777), "u=,g=,o=");
+    }
+}
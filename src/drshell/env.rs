@@ -40,7 +40,12 @@ impl Exe {
 //     return None;
 // }
 
+/* a name with a slash is a path, the others are looked up in $PATH */
 pub fn if_executable(file: &str) -> Option<PathBuf> {
+    if file.contains('/') {
+        let path = PathBuf::from(file);
+        return if_exe(&path).map(|_| path);
+    }
     let Ok(path_var) = env::var("PATH") else {
         return None;
     };
@@ -53,6 +58,42 @@ pub fn if_executable(file: &str) -> Option<PathBuf> {
     None
 }
 
+pub enum ExeFindFail {
+    NotFound,
+    NoSuchFile,
+    IsDirectory,
+    PermissionDenied,
+}
+
+/* like if_executable, but tells why a cmd can't run */
+pub fn find_command(file: &str) -> Result<PathBuf, ExeFindFail> {
+    if file.contains('/') {
+        let path = PathBuf::from(file);
+        return match fs::metadata(&path) {
+            Err(_) => Err(ExeFindFail::NoSuchFile),
+            Ok(metadata) if metadata.is_dir() => Err(ExeFindFail::IsDirectory),
+            Ok(_) if if_exe(&path).is_some() => Ok(path),
+            Ok(_) => Err(ExeFindFail::PermissionDenied),
+        };
+    }
+    if let Some(path) = if_executable(file) {
+        return Ok(path);
+    }
+    /* a file in $PATH without the permission is reported */
+    match find_file_in_path(file) {
+        Some(_) => Err(ExeFindFail::PermissionDenied),
+        None => Err(ExeFindFail::NotFound),
+    }
+}
+
+/* the sourced files only need to be readable */
+pub fn find_file_in_path(file: &str) -> Option<PathBuf> {
+    let path_var = env::var("PATH").ok()?;
+    env::split_paths(&path_var)
+        .map(|dir| dir.join(file))
+        .find(|path| path.is_file())
+}
+
 /* ??? duplicate ??? */
 pub fn find_exes() -> Vec<Exe> {
     let mut exes = Vec::new();
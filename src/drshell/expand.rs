@@ -0,0 +1,405 @@
+use super::options::{self, ShellOpt};
+use super::vars;
+
+const DEFAULT_IFS: &str = " \t\n";
+
+/* $IFS, space, tab and newline when it is unset */
+pub fn ifs() -> String {
+    vars::get_var("IFS").unwrap_or(DEFAULT_IFS.to_string())
+}
+
+pub enum ExpandFail {
+    UnboundVariable(String),
+}
+
+struct Fields {
+    fields: Vec<String>,
+    current: String,
+    has_current: bool,
+    /* after a char of $IFS other than whitespace, a next one ends an empty field */
+    delimited: bool,
+    ifs: String,
+}
+
+impl Fields {
+    fn new() -> Self {
+        Self {
+            fields: Vec::new(),
+            current: String::new(),
+            has_current: false,
+            delimited: true,
+            ifs: ifs(),
+        }
+    }
+
+    fn push(&mut self, c: char) {
+        self.current.push(c);
+        self.has_current = true;
+    }
+
+    fn push_str(&mut self, s: &str) {
+        self.current.push_str(s);
+        self.has_current = true;
+    }
+
+    /* the unquoted results of the expansions are split by $IFS: a run of its
+     * whitespace is one separator, each of its other chars ends a field */
+    fn push_split(&mut self, s: &str) {
+        for c in s.chars() {
+            if !self.ifs.contains(c) {
+                self.push(c);
+            } else if DEFAULT_IFS.contains(c) {
+                if self.has_current {
+                    self.end_field();
+                    self.delimited = false;
+                }
+            } else {
+                if self.delimited {
+                    self.has_current = true;
+                }
+                self.end_field();
+                self.delimited = true;
+            }
+        }
+    }
+
+    fn end_field(&mut self) {
+        if self.has_current {
+            self.fields.push(std::mem::take(&mut self.current));
+            self.has_current = false;
+        }
+    }
+}
+
+enum Param {
+    Name(String),
+    /* $@ */
+    All,
+    /* ${NAME[N]} */
+    Element(String, usize),
+    /* ${NAME[@]} is split like $@, ${NAME[*]} like $* */
+    Elements(String, bool),
+}
+
+/* NAME[N], NAME[@] or NAME[*] inside the braces */
+fn parse_element(name: &str) -> Option<Param> {
+    let (name, index) = name.strip_suffix(']')?.split_once('[')?;
+    if !vars::valid_name(name) {
+        return None;
+    }
+    match index {
+        "@" => Some(Param::Elements(name.to_string(), true)),
+        "*" => Some(Param::Elements(name.to_string(), false)),
+        _ => index
+            .parse()
+            .ok()
+            .map(|index| Param::Element(name.to_string(), index)),
+    }
+}
+
+/* parse the parameter after the '$' at chars[i] */
+fn parse_param(chars: &[char], i: usize) -> Option<(Param, usize)> {
+    let next = *chars.get(i + 1)?;
+    let name_char = |c: &char| c.is_ascii_alphanumeric() || *c == '_';
+
+    let (name, end) = if next == '{' {
+        let len = chars[i + 2..].iter().position(|c| *c == '}')?;
+        let name = chars[i + 2..i + 2 + len].iter().collect::<String>();
+        if let Some(param) = parse_element(&name) {
+            return Some((param, i + 3 + len));
+        }
+        (name, i + 3 + len)
+    } else if next.is_ascii_alphabetic() || next == '_' {
+        let len = chars[i + 1..].iter().take_while(|c| name_char(c)).count();
+        (chars[i + 1..i + 1 + len].iter().collect(), i + 1 + len)
+    } else if next.is_ascii_digit() || "@*#?$!-".contains(next) {
+        (next.to_string(), i + 2)
+    } else {
+        return None;
+    };
+
+    let valid = name.chars().all(|c| name_char(&c))
+        || (name.chars().count() == 1 && "@*#?$!-".contains(name.as_str()));
+    if name.is_empty() || !valid {
+        return None;
+    }
+    if name == "@" {
+        return Some((Param::All, end));
+    }
+    Some((Param::Name(name), end))
+}
+
+fn expand_param(param: Param, quoted: bool, fields: &mut Fields) -> Result<(), ExpandFail> {
+    match param {
+        Param::Name(name) => {
+            let value = if name == "*" && quoted {
+                let separator = fields.ifs.chars().next().map(String::from);
+                vars::positional_params().join(&separator.unwrap_or_default())
+            } else {
+                match vars::get_var(&name) {
+                    Some(value) => value,
+                    None if options::enabled(ShellOpt::Nounset) => {
+                        return Err(ExpandFail::UnboundVariable(name));
+                    }
+                    None => String::new(),
+                }
+            };
+            if quoted {
+                fields.push_str(&value);
+            } else {
+                fields.push_split(&value);
+            }
+        }
+        Param::Element(name, index) => {
+            let value = match vars::get_array(&name) {
+                Some(array) => array.get(index).cloned(),
+                None if index == 0 => vars::get_var(&name),
+                None => None,
+            };
+            let value = match value {
+                Some(value) => value,
+                None if options::enabled(ShellOpt::Nounset) => {
+                    return Err(ExpandFail::UnboundVariable(format!("{}[{}]", name, index)));
+                }
+                None => String::new(),
+            };
+            if quoted {
+                fields.push_str(&value);
+            } else {
+                fields.push_split(&value);
+            }
+        }
+        Param::Elements(name, all) => {
+            let values = vars::get_array(&name)
+                .or_else(|| vars::get_var(&name).map(|value| vec![value]))
+                .unwrap_or_default();
+            if quoted && !all {
+                let separator = fields.ifs.chars().next().map(String::from);
+                fields.push_str(&values.join(&separator.unwrap_or_default()));
+            } else {
+                expand_all(&values, quoted, fields);
+            }
+        }
+        Param::All => expand_all(&vars::positional_params(), quoted, fields),
+    }
+    Ok(())
+}
+
+/* the values are separate fields, also when they are quoted */
+fn expand_all(values: &[String], quoted: bool, fields: &mut Fields) {
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            if quoted {
+                fields.has_current = true;
+            }
+            fields.end_field();
+        }
+        if quoted {
+            fields.push_str(value);
+        } else {
+            fields.push_split(value);
+        }
+    }
+}
+
+/* remove the quotes and expand the parameters of a word */
+pub fn expand_word(raw: &str) -> Result<Vec<String>, ExpandFail> {
+    let chars: Vec<char> = raw.chars().collect();
+    let mut fields = Fields::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '\'' => {
+                i += 1;
+                while i < chars.len() && chars[i] != '\'' {
+                    fields.push(chars[i]);
+                    i += 1;
+                }
+                fields.has_current = true;
+                i += 1;
+            }
+            '"' => {
+                i += 1;
+                /* "$@" with no params expands to nothing, like "${NAME[@]}" of an empty array */
+                let only_all = chars.get(i) == Some(&'$')
+                    && matches!(
+                        parse_param(&chars, i),
+                        Some((Param::All | Param::Elements(_, true), end)) if chars.get(end) == Some(&'"')
+                    );
+                if !only_all {
+                    fields.has_current = true;
+                }
+                while i < chars.len() && chars[i] != '"' {
+                    match chars[i] {
+                        '\\' if i + 1 < chars.len() && "$`\"\\\n".contains(chars[i + 1]) => {
+                            fields.push(chars[i + 1]);
+                            i += 2;
+                        }
+                        '$' => match parse_param(&chars, i) {
+                            Some((param, end)) => {
+                                expand_param(param, true, &mut fields)?;
+                                i = end;
+                            }
+                            None => {
+                                fields.push('$');
+                                i += 1;
+                            }
+                        },
+                        c => {
+                            fields.push(c);
+                            i += 1;
+                        }
+                    }
+                }
+                i += 1;
+            }
+            '\\' => {
+                if let Some(c) = chars.get(i + 1) {
+                    fields.push(*c);
+                }
+                i += 2;
+            }
+            '$' => match parse_param(&chars, i) {
+                Some((param, end)) => {
+                    expand_param(param, false, &mut fields)?;
+                    i = end;
+                }
+                None => {
+                    fields.push('$');
+                    i += 1;
+                }
+            },
+            c => {
+                fields.push(c);
+                i += 1;
+            }
+        }
+    }
+    fields.end_field();
+
+    Ok(fields.fields)
+}
+
+pub fn expand_words(raws: &[String]) -> Result<Vec<String>, ExpandFail> {
+    let mut words = vec![];
+    for raw in raws {
+        words.extend(expand_word(raw)?);
+    }
+    Ok(words)
+}
+
+/* the word is not split, used by the file names of the redirections */
+pub fn expand_word_single(raw: &str) -> Result<String, ExpandFail> {
+    Ok(expand_word(raw)?.join(" "))
+}
+
+pub fn expand_fail_process(reason: ExpandFail) -> i32 {
+    match reason {
+        ExpandFail::UnboundVariable(name) => {
+            eprintln!("drshell: {}: unbound variable", name);
+        }
+    }
+    1
+}
+
+/* quote a word to be read back as itself */
+pub fn quote(word: &str) -> String {
+    "'".to_string() + &word.replace('\'', "'\\''") + "'"
+}
+
+/* quote a word only if it has the special chars, used by the traces */
+pub fn quote_if_needed(word: &str) -> String {
+    let plain = |c: char| c.is_ascii_alphanumeric() || "-_./=:,+@%^".contains(c);
+    if !word.is_empty() && word.chars().all(plain) {
+        word.to_string()
+    } else {
+        quote(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Mutex, MutexGuard};
+
+    /* the params and $IFS are global, so the tests run one at a time */
+    static LOCK: Mutex<()> = Mutex::new(());
+
+    fn setup(params: &[&str], ifs: &str) -> MutexGuard<'static, ()> {
+        let guard = LOCK.lock().unwrap_or_else(|err| err.into_inner());
+        vars::set_positional_params(params.iter().map(|param| param.to_string()).collect());
+        vars::set_var("IFS", ifs);
+        guard
+    }
+
+    fn expand(raw: &str) -> Vec<String> {
+        match expand_word(raw) {
+            Ok(words) => words,
+            Err(ExpandFail::UnboundVariable(name)) => panic!("{}: unbound variable", name),
+        }
+    }
+
+    #[test]
+    fn quoted_all_keeps_each_param() {
+        let _guard = setup(&["a b", "c"], DEFAULT_IFS);
+        assert_eq!(expand("\"$@\""), ["a b", "c"]);
+        assert_eq!(expand("\"x$@y\""), ["xa b", "cy"]);
+    }
+
+    #[test]
+    fn quoted_star_joins_with_ifs() {
+        let _guard = setup(&["a b", "c"], DEFAULT_IFS);
+        assert_eq!(expand("\"$*\""), ["a b c"]);
+        vars::set_var("IFS", ":-");
+        assert_eq!(expand("\"$*\""), ["a b:c"]);
+    }
+
+    #[test]
+    fn empty_quoted_all_is_no_word() {
+        let _guard = setup(&[], DEFAULT_IFS);
+        assert!(expand("\"$@\"").is_empty());
+        assert_eq!(expand("\"$*\""), [""]);
+        assert_eq!(expand("\"\""), [""]);
+    }
+
+    #[test]
+    fn unquoted_params_are_split_by_ifs() {
+        let _guard = setup(&["a b", "c"], DEFAULT_IFS);
+        assert_eq!(expand("$@"), ["a", "b", "c"]);
+        assert_eq!(expand("$*"), ["a", "b", "c"]);
+        assert_eq!(expand("$1"), ["a", "b"]);
+        assert_eq!(expand("\"$1\""), ["a b"]);
+    }
+
+    #[test]
+    fn custom_ifs_splits_values() {
+        let _guard = setup(&["a:b c"], ":");
+        assert_eq!(expand("$1"), ["a", "b c"]);
+        assert_eq!(expand("'$1'"), ["$1"]);
+    }
+
+    #[test]
+    fn empty_fields_between_delimiters() {
+        let _guard = setup(&["a::b", ":c:", "d : e"], ":");
+        assert_eq!(expand("$1"), ["a", "", "b"]);
+        assert_eq!(expand("$2"), ["", "c"]);
+        assert_eq!(expand("$*"), ["a", "", "b ", "c", " d ", " e"]);
+        vars::set_var("IFS", " :");
+        assert_eq!(expand("$3"), ["d", "e"]);
+        assert_eq!(expand("x$2"), ["x", "c"]);
+    }
+
+    #[test]
+    fn array_elements() {
+        let _guard = setup(&[], DEFAULT_IFS);
+        vars::set_array("TEST_ARRAY", vec!["a b".to_string(), "c".to_string()]);
+        assert_eq!(expand("\"${TEST_ARRAY[@]}\""), ["a b", "c"]);
+        assert_eq!(expand("\"${TEST_ARRAY[*]}\""), ["a b c"]);
+        assert_eq!(expand("${TEST_ARRAY[0]}"), ["a", "b"]);
+        assert_eq!(expand("\"${TEST_ARRAY[1]}\""), ["c"]);
+        assert_eq!(expand("\"$TEST_ARRAY\""), ["a b"]);
+        vars::set_array("TEST_ARRAY", vec![]);
+        assert!(expand("\"${TEST_ARRAY[@]}\"").is_empty());
+    }
+}
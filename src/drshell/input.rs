@@ -1,65 +1,387 @@
+use super::commands::{self, Api};
+use super::expand;
 use super::operators::Opt;
+use super::vars;
 
 pub struct CmdsAndOperators {
     pub cmds: Vec<Vec<String>>,
-    pub operators: Vec<Opt>,
+    /* the redirections of each cmd */
+    pub operators: Vec<Vec<Opt>>,
+    /* ! inverts the status */
+    pub negated: bool,
+    /* time reports the times of the pipeline, Some(true) with -p */
+    pub time: Option<bool>,
+}
+
+/* the operator before a pipeline of a list */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connector {
+    Seq,
+    And,
+    Or,
+}
+
+pub struct ListItem {
+    pub connector: Connector,
+    pub pipeline: CmdsAndOperators,
+    /* the and-or list ending with this pipeline is followed by & */
+    pub background: bool,
 }
 
 pub enum InputSplitFail {
     NoInput,
     NoRedirectArg,
     NoAppendArg,
+    BadFd,
     NoPipCmd,
-    ShlexError,
+    NoListCmd,
+    UnmatchedQuote,
+    UnmatchedBrace,
 }
 
 struct CmdsNoSplitAndOperators {
     cmds: Vec<String>,
-    operators: Vec<Opt>,
+    operators: Vec<Vec<Opt>>,
+}
+
+/* the words keep their quotes, they are removed by the expansion */
+#[derive(Debug)]
+enum Token {
+    Word(String),
+    Operator(String),
+}
+
+fn end_word(word: &mut String, tokens: &mut Vec<Token>) {
+    if !word.is_empty() {
+        tokens.push(Token::Word(std::mem::take(word)));
+    }
+}
+
+/* NAME or NAME[N] from {NAME} or {NAME[N]} */
+fn fd_var(word: &str) -> Option<&str> {
+    let var = word.strip_prefix('{')?.strip_suffix('}')?;
+    let name = match var.split_once('[') {
+        Some((name, index)) => {
+            let index = index.strip_suffix(']')?;
+            if index.is_empty() || !index.chars().all(|c| c.is_ascii_digit()) {
+                return None;
+            }
+            name
+        }
+        None => var,
+    };
+    if vars::valid_name(name) {
+        Some(var)
+    } else {
+        None
+    }
 }
 
-fn split_operators(input: String) -> Result<CmdsNoSplitAndOperators, InputSplitFail> {
-    let input = input.trim_end();
-    let input = shlex::split(input).ok_or(InputSplitFail::ShlexError)?;
+fn tokenize(input: &str) -> Result<Vec<Token>, InputSplitFail> {
+    let mut tokens = vec![];
+    let mut word = String::new();
+    let mut chars = input.chars().peekable();
 
-    let last = input.last();
-    match last {
-        Some(last) => {
-            let last = last.as_str();
-            if last == ">" || last == "1>" || last == "2>" {
-                return Err(InputSplitFail::NoRedirectArg);
-            } else if last == ">>" || last == "1>>" || last == "2>>" {
-                return Err(InputSplitFail::NoAppendArg);
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' | '\r' => end_word(&mut word, &mut tokens),
+            '\n' | ';' => {
+                end_word(&mut word, &mut tokens);
+                tokens.push(Token::Operator(c.to_string()));
             }
+            '&' => {
+                end_word(&mut word, &mut tokens);
+                if chars.next_if_eq(&'&').is_some() {
+                    tokens.push(Token::Operator("&&".to_string()));
+                } else {
+                    tokens.push(Token::Operator("&".to_string()));
+                }
+            }
+            '#' if word.is_empty() => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break;
+                    }
+                }
+            }
+            '\'' => {
+                word.push(c);
+                loop {
+                    let c = chars.next().ok_or(InputSplitFail::UnmatchedQuote)?;
+                    word.push(c);
+                    if c == '\'' {
+                        break;
+                    }
+                }
+            }
+            '"' => {
+                word.push(c);
+                loop {
+                    let c = chars.next().ok_or(InputSplitFail::UnmatchedQuote)?;
+                    word.push(c);
+                    if c == '\\' {
+                        if let Some(c) = chars.next() {
+                            word.push(c);
+                        }
+                    } else if c == '"' {
+                        break;
+                    }
+                }
+            }
+            '\\' => {
+                word.push(c);
+                if let Some(c) = chars.next() {
+                    word.push(c);
+                }
+            }
+            '|' => {
+                end_word(&mut word, &mut tokens);
+                if chars.next_if_eq(&'|').is_some() {
+                    tokens.push(Token::Operator("||".to_string()));
+                } else {
+                    tokens.push(Token::Operator("|".to_string()));
+                }
+            }
+            '>' | '<' => {
+                /* the digits before the operator are the fd, or {NAME} with the fd in $NAME */
+                let mut operator = String::new();
+                let digits = !word.is_empty() && word.chars().all(|c| c.is_ascii_digit());
+                if digits || fd_var(&word).is_some() {
+                    operator = std::mem::take(&mut word);
+                } else {
+                    end_word(&mut word, &mut tokens);
+                }
+                operator.push(c);
+                let next: &[char] = if c == '>' {
+                    &['>', '|', '&']
+                } else {
+                    &['>', '&']
+                };
+                if let Some(c) = chars.next_if(|c| next.contains(c)) {
+                    operator.push(c);
+                }
+                tokens.push(Token::Operator(operator));
+            }
+            _ => word.push(c),
+        }
+    }
+    end_word(&mut word, &mut tokens);
+
+    Ok(tokens)
+}
+
+/* replace the aliases at the command positions, return whether the next word is checked too */
+fn expand_aliases(tokens: Vec<Token>, expanding: &mut Vec<String>) -> (Vec<Token>, bool) {
+    let mut result = vec![];
+    let mut check = true;
+
+    for token in tokens {
+        let word = match token {
+            Token::Word(word) => word,
+            Token::Operator(operator) => {
+                /* the word after a redirection is a file */
+                check = !operator.contains(['>', '<']);
+                result.push(Token::Operator(operator));
+                continue;
+            }
+        };
+        if check && !expanding.contains(&word) {
+            if let Some(value) = commands::api(Api::FindAlias(&word)) {
+                if let Ok(value_tokens) = tokenize(&value) {
+                    expanding.push(word);
+                    let (value_tokens, check_next) = expand_aliases(value_tokens, expanding);
+                    expanding.pop();
+                    result.extend(value_tokens);
+                    check = check_next || value.ends_with([' ', '\t']);
+                    continue;
+                }
+            }
+        }
+        /* the words after !, time and time -p are commands too */
+        let after_time = matches!(result.last(), Some(Token::Word(last)) if last == "time");
+        check = word == "!" || word == "time" || (word == "-p" && after_time);
+        result.push(Token::Word(word));
+    }
+
+    (result, check)
+}
+
+fn list_operator(token: &Token) -> bool {
+    matches!(token, Token::Operator(operator) if ["\n", ";", "&", "&&", "||"].contains(&operator.as_str()))
+}
+
+/* coproc [NAME] { LIST; } and coproc CMD become the words coproc, NAME and the quoted
+ * text of the cmds, which the builtin runs in a forked shell */
+fn group_coprocs(tokens: Vec<Token>) -> Result<Vec<Token>, InputSplitFail> {
+    let mut result: Vec<Token> = vec![];
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(token) = tokens.next() {
+        let command_position = result.last().map_or(true, |last| {
+            list_operator(last) || matches!(last, Token::Operator(operator) if operator == "|")
+        });
+        if !command_position || !matches!(&token, Token::Word(word) if word == "coproc") {
+            result.push(token);
+            continue;
+        }
+        let mut rest = vec![];
+        while let Some(token) = tokens.next_if(|token| !list_operator(token)) {
+            rest.push(token);
+        }
+        let (name, start) = match rest.as_slice() {
+            [Token::Word(brace), ..] if brace == "{" => ("COPROC".to_string(), 1),
+            [Token::Word(name), Token::Word(brace), ..]
+                if brace == "{" && vars::valid_name(name) =>
+            {
+                (name.to_string(), 2)
+            }
+            [] => return Err(InputSplitFail::NoListCmd),
+            _ => {
+                result.extend(coproc_words("COPROC".to_string(), &rest));
+                continue;
+            }
+        };
+
+        /* the list ends at the } after a ; or a new line */
+        let mut list = vec![];
+        let mut depth = 1;
+        let mut rest = rest.into_iter().skip(start);
+        for token in rest.by_ref().chain(tokens.by_ref()) {
+            let command_position = list.last().map_or(true, list_operator);
+            match &token {
+                Token::Word(word) if command_position && word == "{" => depth += 1,
+                Token::Word(word) if command_position && word == "}" => depth -= 1,
+                _ => {}
+            }
+            if depth == 0 {
+                break;
+            }
+            list.push(token);
+        }
+        if depth != 0 {
+            return Err(InputSplitFail::UnmatchedBrace);
+        }
+        while matches!(list.last(), Some(Token::Operator(operator)) if operator == ";" || operator == "\n")
+        {
+            list.pop();
+        }
+        if list.is_empty() {
+            return Err(InputSplitFail::NoListCmd);
         }
-        None => return Err(InputSplitFail::NoInput),
+        result.extend(coproc_words(name, &list));
+        /* the redirections after } are the coproc's */
+        result.extend(rest);
     }
 
-    let mut input = input.iter();
+    Ok(result)
+}
+
+fn coproc_words(name: String, cmds: &[Token]) -> [Token; 3] {
+    let text: Vec<&str> = cmds
+        .iter()
+        .map(|token| match token {
+            Token::Word(word) | Token::Operator(word) => word.as_str(),
+        })
+        .collect();
+    [
+        Token::Word("coproc".to_string()),
+        Token::Word(name),
+        Token::Word(expand::quote(&text.join(" "))),
+    ]
+}
+
+/* split the tokens by the list operators, with whether each one is followed by & */
+fn split_lists(tokens: Vec<Token>) -> Result<Vec<(Connector, Vec<Token>, bool)>, InputSplitFail> {
+    let mut lists = vec![];
+    let mut connector = Connector::Seq;
+    let mut list = vec![];
+
+    for token in tokens {
+        let next_connector = match &token {
+            Token::Operator(operator) if operator == "\n" => {
+                /* the blank lines and the lines after && or || */
+                if list.is_empty() {
+                    continue;
+                }
+                Connector::Seq
+            }
+            Token::Operator(operator) if operator == ";" || operator == "&" => Connector::Seq,
+            Token::Operator(operator) if operator == "&&" => Connector::And,
+            Token::Operator(operator) if operator == "||" => Connector::Or,
+            _ => {
+                list.push(token);
+                continue;
+            }
+        };
+        if list.is_empty() {
+            return Err(InputSplitFail::NoListCmd);
+        }
+        let background = matches!(&token, Token::Operator(operator) if operator == "&");
+        lists.push((connector, std::mem::take(&mut list), background));
+        connector = next_connector;
+    }
+
+    if !list.is_empty() {
+        lists.push((connector, list, false));
+    } else if connector != Connector::Seq {
+        return Err(InputSplitFail::NoListCmd);
+    }
+
+    Ok(lists)
+}
+
+fn split_operators(input: Vec<Token>) -> Result<CmdsNoSplitAndOperators, InputSplitFail> {
+    let mut input = input.into_iter();
     let mut next;
-    let mut operators: Vec<Opt> = Vec::new();
+    let mut operators: Vec<Vec<Opt>> = vec![vec![]];
     let mut cmds: Vec<String> = Vec::new();
 
     loop {
         next = input.next();
         match next {
-            Some(string) => {
-                let str = string.as_str();
-                if str == ">" || str == "1>" {
-                    operators.push(Opt::RedirectStdout(
-                        input.next().expect("never").to_string(),
-                    ));
-                } else if str == "2>" {
-                    operators.push(Opt::RedirectStderr(
-                        input.next().expect("never").to_string(),
-                    ));
-                } else if str == ">>" || str == "1>>" {
-                    operators.push(Opt::AppendStdout(input.next().expect("never").to_string()));
-                } else if str == "2>>" {
-                    operators.push(Opt::AppendStderr(input.next().expect("never").to_string()));
-                } else {
-                    cmds.push(string.to_string());
+            Some(Token::Word(word)) => cmds.push(word),
+            Some(Token::Operator(operator)) => {
+                let str = operator.as_str();
+                if str == "|" {
+                    cmds.push(operator);
+                    operators.push(vec![]);
+                    continue;
+                }
+                let operators = operators.last_mut().expect("never");
+                let Some(Token::Word(file)) = input.next() else {
+                    if str.ends_with(">>") {
+                        return Err(InputSplitFail::NoAppendArg);
+                    }
+                    return Err(InputSplitFail::NoRedirectArg);
+                };
+                /* only closing the fd in a variable, {NAME}>&- or {NAME}<&- */
+                if str.starts_with('{') {
+                    let (var, str) = str.split_at(str.find('}').expect("never") + 1);
+                    if !matches!(str, ">&" | "<&") || file != "-" {
+                        return Err(InputSplitFail::BadFd);
+                    }
+                    let var = fd_var(var).expect("never");
+                    operators.push(Opt::CloseVar(var.to_string()));
+                    continue;
                 }
+                let digits = str.chars().take_while(|c| c.is_ascii_digit()).count();
+                let (fd, str) = str.split_at(digits);
+                let fd = if fd.is_empty() {
+                    None
+                } else {
+                    Some(fd.parse::<i32>().map_err(|_| InputSplitFail::BadFd)?)
+                };
+                let output = fd.unwrap_or(1);
+                let input = fd.unwrap_or(0);
+                operators.push(match str {
+                    ">" => Opt::Redirect(output, file),
+                    ">>" => Opt::Append(output, file),
+                    ">|" => Opt::Clobber(output, file),
+                    ">&" => Opt::DupOutput(output, file),
+                    "<" => Opt::Read(input, file),
+                    "<>" => Opt::ReadWrite(input, file),
+                    _ => Opt::DupInput(input, file),
+                });
             }
             None => break,
         }
@@ -125,30 +447,240 @@ fn split_cmds(cmds: Vec<String>) -> Result<Vec<Vec<String>>, InputSplitFail> {
     Ok(cmds_split)
 }
 
-pub fn split_input(input: String) -> Result<CmdsAndOperators, InputSplitFail> {
-    let cmds_no_split_and_operators = split_operators(input)?;
-    let cmds_split = split_cmds(cmds_no_split_and_operators.cmds)?;
+/* NAME=VALUE, the VALUE is not expanded yet */
+pub fn split_assignment(word: &str) -> Option<(&str, &str)> {
+    let (name, value) = word.split_once('=')?;
+    if vars::valid_name(name) {
+        Some((name, value))
+    } else {
+        None
+    }
+}
+
+pub fn split_input(input: String) -> Result<Vec<ListItem>, InputSplitFail> {
+    let tokens = tokenize(&input)?;
+    let (tokens, _) = expand_aliases(tokens, &mut vec![]);
+    let tokens = group_coprocs(tokens)?;
+    let mut items = vec![];
+
+    for (connector, tokens, background) in split_lists(tokens)? {
+        let mut cmds_no_split_and_operators = split_operators(tokens)?;
+        let cmds = &mut cmds_no_split_and_operators.cmds;
+        let mut time = None;
+        if cmds.first().map(String::as_str) == Some("time") {
+            cmds.remove(0);
+            let posix = cmds.first().map(String::as_str) == Some("-p");
+            if posix {
+                cmds.remove(0);
+            }
+            time = Some(posix);
+        }
+        let negated = cmds.first().map(String::as_str) == Some("!");
+        if negated {
+            cmds.remove(0);
+        }
+        let cmds_split = split_cmds(cmds_no_split_and_operators.cmds)?;
+        items.push(ListItem {
+            connector,
+            pipeline: CmdsAndOperators {
+                cmds: cmds_split,
+                operators: cmds_no_split_and_operators.operators,
+                negated,
+                time,
+            },
+            background,
+        });
+    }
+    if items.is_empty() {
+        return Err(InputSplitFail::NoInput);
+    }
 
-    Ok(CmdsAndOperators {
-        cmds: cmds_split,
-        operators: cmds_no_split_and_operators.operators,
-    })
+    Ok(items)
 }
 
-pub fn split_fail_process(reason: InputSplitFail) {
+pub fn split_fail_process(reason: InputSplitFail) -> i32 {
     match reason {
-        InputSplitFail::NoInput => {}
+        InputSplitFail::NoInput => return 0,
         InputSplitFail::NoRedirectArg => {
             eprintln!("no file to redirect!!!");
         }
         InputSplitFail::NoAppendArg => {
             eprintln!("no file to append!!!");
         }
+        InputSplitFail::BadFd => {
+            eprintln!("bad file descriptor to redirect!!!");
+        }
         InputSplitFail::NoPipCmd => {
             eprintln!("no command for the pips!!!")
         }
-        InputSplitFail::ShlexError => {
-            eprintln!("shlex error!!!")
+        InputSplitFail::NoListCmd => {
+            eprintln!("no command for the list operators!!!")
         }
+        InputSplitFail::UnmatchedQuote => {
+            eprintln!("unmatched quote!!!")
+        }
+        InputSplitFail::UnmatchedBrace => {
+            eprintln!("unmatched brace!!!")
+        }
+    }
+    2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<Token> {
+        tokenize(input).unwrap_or_else(|_| panic!("{}: not tokenized", input))
+    }
+
+    /* the words and the redirections of a simple command */
+    fn split(input: &str) -> (Vec<String>, Vec<String>) {
+        let split =
+            split_operators(tokens(input)).unwrap_or_else(|_| panic!("{}: not split", input));
+        let opts = split
+            .operators
+            .iter()
+            .flatten()
+            .map(|opt| opt.operator() + " " + opt.file())
+            .collect();
+        (split.cmds, opts)
+    }
+
+    /* the words and the operators, in order */
+    fn texts(tokens: &[Token]) -> Vec<&str> {
+        tokens
+            .iter()
+            .map(|token| match token {
+                Token::Word(text) | Token::Operator(text) => text.as_str(),
+            })
+            .collect()
+    }
+
+    fn bad_fd(input: &str) -> bool {
+        matches!(split_operators(tokens(input)), Err(InputSplitFail::BadFd))
+    }
+
+    #[test]
+    fn close_fd_in_variable() {
+        assert_eq!(split("exec {UP[1]}>&-").1, ["{UP[1]}>& -"]);
+        assert_eq!(split("exec {fd}<&-").1, ["{fd}>& -"]);
+        /* only the closing form */
+        assert!(bad_fd("exec {fd}>file"));
+        assert!(bad_fd("exec {fd}>&2"));
+        /* braces around something else are a word */
+        assert_eq!(split("echo {1x}>f").0, ["echo", "{1x}"]);
+    }
+
+    #[test]
+    fn aliases_at_command_positions() {
+        let aliases = [
+            "t_ll=ls -l",
+            "t_sudo=sudo ",
+            "t_loop=t_loop x",
+            "t_a=t_b 1",
+            "t_b=echo",
+        ];
+        commands::eval(commands::Cmd::Alias(aliases.map(String::from).to_vec()));
+        let expand = |input| texts(&expand_aliases(tokens(input), &mut vec![]).0).join(" ");
+
+        assert_eq!(expand("t_ll a; t_ll | t_ll"), "ls -l a ; ls -l | ls -l");
+        /* the next word too when the value ends with a blank */
+        assert_eq!(expand("t_ll t_ll"), "ls -l t_ll");
+        assert_eq!(expand("t_sudo t_ll"), "sudo ls -l");
+        /* an alias in the value is expanded, except the one being expanded */
+        assert_eq!(expand("t_a 2"), "echo 1 2");
+        assert_eq!(expand("t_loop"), "t_loop x");
+        /* not the file of a redirection, but the cmds after ! and time */
+        assert_eq!(expand("echo > t_ll"), "echo > t_ll");
+        assert_eq!(expand("! t_ll"), "! ls -l");
+        assert_eq!(expand("time -p t_ll"), "time -p ls -l");
+    }
+
+    #[test]
+    fn redirections_with_default_fds() {
+        let (cmds, opts) = split("cmd a > out >> log >| force < in b <> rw");
+        assert_eq!(cmds, ["cmd", "a", "b"]);
+        assert_eq!(opts, ["> out", ">> log", ">| force", "< in", "<> rw"]);
+        assert_eq!(split("a | b > f").0, ["a", "|", "b"]);
+        assert!(matches!(
+            split_operators(tokens("echo >")),
+            Err(InputSplitFail::NoRedirectArg)
+        ));
+        assert!(matches!(
+            split_operators(tokens("echo >>")),
+            Err(InputSplitFail::NoAppendArg)
+        ));
+    }
+
+    #[test]
+    fn lists_with_connectors() {
+        let lists =
+            split_lists(tokens("a && b || c; d & e\n\nf")).unwrap_or_else(|_| panic!("not split"));
+        let lists: Vec<(Connector, String, bool)> = lists
+            .iter()
+            .map(|(connector, tokens, background)| {
+                (*connector, texts(tokens).join(" "), *background)
+            })
+            .collect();
+        assert_eq!(
+            lists,
+            [
+                (Connector::Seq, "a".to_string(), false),
+                (Connector::And, "b".to_string(), false),
+                (Connector::Or, "c".to_string(), false),
+                (Connector::Seq, "d".to_string(), true),
+                (Connector::Seq, "e".to_string(), false),
+                (Connector::Seq, "f".to_string(), false),
+            ]
+        );
+        /* a new line after && continues the list, an operator needs a cmd before it */
+        assert_eq!(
+            split_lists(tokens("a &&\nb")).map(|lists| lists.len()).ok(),
+            Some(2)
+        );
+        assert!(matches!(
+            split_lists(tokens("; a")),
+            Err(InputSplitFail::NoListCmd)
+        ));
+        assert!(matches!(
+            split_lists(tokens("a &&")),
+            Err(InputSplitFail::NoListCmd)
+        ));
+    }
+
+    #[test]
+    fn fd_before_operator() {
+        let texts_of = |input| texts(&tokens(input)).join(" ");
+        assert_eq!(texts_of("cmd 2>&1 3<&- 10>f"), "cmd 2>& 1 3<& - 10> f");
+        /* only unquoted digits right before the operator */
+        assert_eq!(texts_of("cmd a2>f 2 >f '2'>f"), "cmd a2 > f 2 > f '2' > f");
+        assert_eq!(texts_of("cmd >|f <>g >>h"), "cmd >| f <> g >> h");
+
+        let (_, opts) = split("cmd 2>&1 >&2 3<&- 4<>f 0<in");
+        assert_eq!(opts, ["2>& 1", ">& 2", "3<& -", "4<> f", "< in"]);
+        assert!(bad_fd("cmd 99999999999>f"));
+    }
+
+    #[test]
+    fn time_and_negation_before_pipeline() {
+        let items = split_input("time -p ! a | b && ! c; time d".to_string())
+            .unwrap_or_else(|_| panic!("not split"));
+        let pipelines: Vec<(Option<bool>, bool, usize)> = items
+            .iter()
+            .map(|item| {
+                let pipeline = &item.pipeline;
+                (pipeline.time, pipeline.negated, pipeline.cmds.len())
+            })
+            .collect();
+        assert_eq!(
+            pipelines,
+            [
+                (Some(true), true, 2),
+                (None, true, 1),
+                (Some(false), false, 1)
+            ]
+        );
+        assert_eq!(items[0].pipeline.cmds[0], ["a"]);
     }
 }
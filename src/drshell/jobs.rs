@@ -0,0 +1,857 @@
+use super::commands;
+use super::options::{self, ShellOpt};
+use super::signals;
+use super::times;
+use super::vars;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::os::unix::process::CommandExt;
+use std::process::{self, Command};
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::Mutex;
+
+/* a process of a job, the wait status is None until it exits */
+#[derive(Clone)]
+struct Proc {
+    pid: i32,
+    status: Option<i32>,
+    /* the signal which stopped it */
+    stopped: Option<i32>,
+}
+
+#[derive(Clone)]
+pub struct Job {
+    /* 0 until the job is added to the table */
+    id: usize,
+    pgid: i32,
+    procs: Vec<Proc>,
+    /* the index of the last cmd, whose status is the job's */
+    last_cmd: usize,
+    command: String,
+    /* whether the last change of the state has been reported */
+    notified: bool,
+    /* the terminal modes of a stopped job */
+    tmodes: Option<libc::termios>,
+    /* disown -h, the job doesn't get SIGHUP */
+    nohup: bool,
+}
+
+pub enum JobFindFail {
+    NoJob,
+    Ambiguous,
+}
+
+lazy_static! {
+    static ref JOBS: Mutex<Vec<Job>> = Mutex::new(vec![]);
+    /* the ids from the oldest to the current job */
+    static ref JOB_ORDER: Mutex<Vec<usize>> = Mutex::new(vec![]);
+    static ref SHELL_TMODES: Mutex<Option<libc::termios>> = Mutex::new(None);
+    /* the children without a job reaped by a wait for any child, like the tees,
+     * with their wait status for the wait by their pid */
+    static ref REAPED: Mutex<HashMap<i32, i32>> = Mutex::new(HashMap::new());
+}
+
+static JOB_CONTROL: AtomicBool = AtomicBool::new(false);
+/* the terminal of the interactive shell, kept open for tcsetpgrp */
+static TTY_FD: AtomicI32 = AtomicI32::new(-1);
+static SHELL_PGID: AtomicI32 = AtomicI32::new(0);
+static ORIGINAL_PGID: AtomicI32 = AtomicI32::new(0);
+/* in a forked shell, running a builtin of a pipeline or a background list */
+static SUBSHELL: AtomicBool = AtomicBool::new(false);
+
+/* the signals ignored by the interactive shell and reset in the jobs */
+const JOB_SIGNALS: [i32; 4] = [libc::SIGTSTP, libc::SIGTTIN, libc::SIGTTOU, libc::SIGQUIT];
+
+/* the status of $? for a wait status */
+pub fn exit_code(wstatus: i32) -> i32 {
+    if libc::WIFEXITED(wstatus) {
+        libc::WEXITSTATUS(wstatus)
+    } else if libc::WIFSIGNALED(wstatus) {
+        128 + libc::WTERMSIG(wstatus)
+    } else {
+        1
+    }
+}
+
+/* waitpid, the times of a reaped child are added to the shell's children times */
+fn wait_child(pid: i32, wstatus: &mut i32, options: i32) -> i32 {
+    if let Some(status) = REAPED.lock().unwrap().remove(&pid) {
+        *wstatus = status;
+        return pid;
+    }
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    let pid = unsafe { libc::wait4(pid, wstatus, options, &mut usage) };
+    if pid > 0 && !libc::WIFSTOPPED(*wstatus) && !libc::WIFCONTINUED(*wstatus) {
+        times::add_child(&usage);
+    }
+    pid
+}
+
+/* Killed, Segmentation fault (core dumped), ... */
+fn signal_notice(wstatus: i32) -> String {
+    let description = signals::signal_description(libc::WTERMSIG(wstatus));
+    if libc::WCOREDUMP(wstatus) {
+        description + " (core dumped)"
+    } else {
+        description
+    }
+}
+
+impl Job {
+    pub fn new(pgid: i32, pids: Vec<i32>, last_cmd: usize, command: String) -> Self {
+        Self {
+            id: 0,
+            pgid,
+            procs: pids
+                .into_iter()
+                .map(|pid| Proc {
+                    pid,
+                    status: None,
+                    stopped: None,
+                })
+                .collect(),
+            last_cmd,
+            command,
+            notified: false,
+            tmodes: None,
+            nohup: false,
+        }
+    }
+
+    pub fn done(&self) -> bool {
+        self.procs.iter().all(|proc| proc.status.is_some())
+    }
+
+    pub fn stopped(&self) -> bool {
+        !self.done()
+            && self
+                .procs
+                .iter()
+                .all(|proc| proc.status.is_some() || proc.stopped.is_some())
+    }
+
+    /* the status of each cmd, a stopped one has 128 + the signal */
+    fn statuses(&self) -> Vec<i32> {
+        self.procs[..=self.last_cmd]
+            .iter()
+            .map(|proc| match (proc.status, proc.stopped) {
+                (Some(wstatus), _) => exit_code(wstatus),
+                (None, Some(signal)) => 128 + signal,
+                (None, None) => 0,
+            })
+            .collect()
+    }
+
+    /* with pipefail, the status of the last failing cmd */
+    pub fn status(&self) -> i32 {
+        let statuses = self.statuses();
+        if options::enabled(ShellOpt::Pipefail) {
+            if let Some(status) = statuses.iter().rfind(|status| **status != 0) {
+                return *status;
+            }
+        }
+        statuses.last().copied().unwrap_or(0)
+    }
+
+    /* record a wait status, return whether the job has the pid */
+    fn update(&mut self, pid: i32, wstatus: i32) -> bool {
+        let Some(proc) = self.procs.iter_mut().find(|proc| proc.pid == pid) else {
+            return false;
+        };
+        if libc::WIFSTOPPED(wstatus) {
+            proc.stopped = Some(libc::WSTOPSIG(wstatus));
+        } else if libc::WIFCONTINUED(wstatus) {
+            proc.stopped = None;
+        } else {
+            proc.status = Some(wstatus);
+        }
+        self.notified = false;
+        true
+    }
+
+    /* the statuses of its procs reaped before it was waited for */
+    fn take_reaped(&mut self) {
+        let mut reaped = REAPED.lock().unwrap();
+        for proc in self.procs.iter_mut().filter(|proc| proc.status.is_none()) {
+            if let Some(wstatus) = reaped.remove(&proc.pid) {
+                proc.status = Some(wstatus);
+            }
+        }
+    }
+
+    fn state(&self) -> String {
+        if self.stopped() {
+            return "Stopped".to_string();
+        }
+        if !self.done() {
+            return "Running".to_string();
+        }
+        let wstatus = self.procs[self.last_cmd].status.unwrap_or(0);
+        if libc::WIFSIGNALED(wstatus) {
+            return signal_notice(wstatus);
+        }
+        match exit_code(wstatus) {
+            0 => "Done".to_string(),
+            status => format!("Exit {}", status),
+        }
+    }
+
+    /* the running jobs are shown with & */
+    fn command_text(&self) -> String {
+        if self.done() || self.stopped() {
+            self.command.clone()
+        } else {
+            self.command.clone() + " &"
+        }
+    }
+
+    /* SIGCONT to all the processes */
+    fn resume(&mut self) {
+        unsafe {
+            if job_control() {
+                libc::kill(-self.pgid, libc::SIGCONT);
+            } else {
+                for proc in &self.procs {
+                    libc::kill(proc.pid, libc::SIGCONT);
+                }
+            }
+        }
+        for proc in &mut self.procs {
+            proc.stopped = None;
+        }
+    }
+
+    /* kill the procs which are still running and reap them */
+    pub fn kill(self) {
+        for proc in self.procs.iter().filter(|proc| proc.status.is_none()) {
+            unsafe { libc::kill(proc.pid, libc::SIGKILL) };
+            let mut wstatus = 0;
+            while wait_child(proc.pid, &mut wstatus, 0) < 0
+                && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted
+            {}
+        }
+    }
+
+    pub fn pgid(&self) -> i32 {
+        self.pgid
+    }
+
+    fn pids(&self) -> Vec<i32> {
+        self.procs.iter().map(|proc| proc.pid).collect()
+    }
+}
+
+pub fn job_control() -> bool {
+    JOB_CONTROL.load(Ordering::SeqCst)
+}
+
+/* take the terminal for the interactive shell, in its own process group */
+pub fn init() {
+    unsafe {
+        let tty = libc::fcntl(0, libc::F_DUPFD_CLOEXEC, 10);
+        if tty < 0 {
+            return;
+        }
+        if libc::isatty(tty) == 0 {
+            libc::close(tty);
+            return;
+        }
+
+        /* wait until the shell is in the foreground */
+        loop {
+            let pgrp = libc::getpgrp();
+            if libc::tcgetpgrp(tty) == pgrp {
+                break;
+            }
+            libc::kill(-pgrp, libc::SIGTTIN);
+        }
+        for signal in JOB_SIGNALS {
+            signals::ignore(signal);
+        }
+
+        ORIGINAL_PGID.store(libc::getpgrp(), Ordering::SeqCst);
+        let pid = libc::getpid();
+        if libc::getpgrp() != pid {
+            libc::setpgid(0, 0);
+        }
+        libc::tcsetpgrp(tty, pid);
+        SHELL_PGID.store(pid, Ordering::SeqCst);
+
+        let mut tmodes: libc::termios = std::mem::zeroed();
+        if libc::tcgetattr(tty, &mut tmodes) == 0 {
+            *SHELL_TMODES.lock().unwrap() = Some(tmodes);
+        }
+        TTY_FD.store(tty, Ordering::SeqCst);
+    }
+    JOB_CONTROL.store(true, Ordering::SeqCst);
+}
+
+/* give the terminal back to the process group which started the shell */
+pub fn release_terminal() {
+    if job_control() {
+        unsafe {
+            libc::tcsetpgrp(
+                TTY_FD.load(Ordering::SeqCst),
+                ORIGINAL_PGID.load(Ordering::SeqCst),
+            );
+        }
+    }
+}
+
+fn reset_job_signals() {
+    for signal in JOB_SIGNALS {
+        signals::set_default(signal);
+    }
+    signals::set_default(libc::SIGINT);
+    signals::set_default(libc::SIGHUP);
+    signals::set_default(libc::SIGTERM);
+}
+
+/* replace the shell with the cmd, which gets the signals of a job,
+ * the shell's ones are restored if the exec fails */
+pub fn exec(cmd: &mut Command) -> io::Error {
+    let mut saved = vec![];
+    for signal in JOB_SIGNALS
+        .into_iter()
+        .chain([libc::SIGINT, libc::SIGHUP, libc::SIGTERM])
+    {
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        unsafe { libc::sigaction(signal, std::ptr::null(), &mut action) };
+        saved.push((signal, action));
+    }
+    if options::interactive() {
+        reset_job_signals();
+    }
+    let err = cmd.exec();
+    for (signal, action) in saved {
+        unsafe { libc::sigaction(signal, &action, std::ptr::null_mut()) };
+    }
+    err
+}
+
+/* put the cmd into the process group pgid, 0 creates a new one */
+pub fn set_process_group(cmd: &mut Command, pgid: i32, foreground: bool) {
+    if !job_control() {
+        return;
+    }
+    let tty = TTY_FD.load(Ordering::SeqCst);
+    unsafe {
+        cmd.pre_exec(move || {
+            libc::setpgid(0, pgid);
+            if foreground {
+                libc::tcsetpgrp(tty, libc::getpgrp());
+            }
+            reset_job_signals();
+            Ok(())
+        });
+    }
+}
+
+/* the parent sets the group too, whichever runs first */
+pub fn join_process_group(pid: i32, pgid: i32) {
+    if job_control() {
+        unsafe {
+            libc::setpgid(pid, if pgid == 0 { pid } else { pgid });
+        }
+    }
+}
+
+pub fn shell_pgid() -> i32 {
+    SHELL_PGID.load(Ordering::SeqCst)
+}
+
+pub fn subshell() -> bool {
+    SUBSHELL.load(Ordering::SeqCst)
+}
+
+/* run f in a forked shell without job control and traps, in the process group pgid,
+ * return the pid */
+pub fn fork_shell(pgid: i32, foreground: bool, f: impl FnOnce() -> i32) -> io::Result<i32> {
+    vars::shell_pid();
+    let _ = io::stdout().flush();
+    let pid = unsafe { libc::fork() };
+    if pid < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if pid > 0 {
+        join_process_group(pid, pgid);
+        return Ok(pid);
+    }
+
+    if job_control() {
+        unsafe {
+            libc::setpgid(0, pgid);
+            if foreground {
+                libc::tcsetpgrp(TTY_FD.load(Ordering::SeqCst), libc::getpgrp());
+            }
+        }
+    }
+    if options::interactive() {
+        reset_job_signals();
+    }
+    JOB_CONTROL.store(false, Ordering::SeqCst);
+    SUBSHELL.store(true, Ordering::SeqCst);
+    JOBS.lock().unwrap().clear();
+    JOB_ORDER.lock().unwrap().clear();
+    commands::reset_traps();
+    /* a write to a closed pipe ends the forked shell like the other cmds */
+    if commands::sigpipe_default() {
+        signals::set_default(libc::SIGPIPE);
+    }
+    let status = f();
+    let _ = io::stdout().flush();
+    process::exit(status);
+}
+
+fn touch(id: usize) {
+    let mut order = JOB_ORDER.lock().unwrap();
+    order.retain(|i| *i != id);
+    order.push(id);
+}
+
+fn add_job(mut job: Job) -> usize {
+    let mut jobs = JOBS.lock().unwrap();
+    if job.id == 0 {
+        job.id = jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+    }
+    let id = job.id;
+    let index = jobs.partition_point(|j| j.id < id);
+    jobs.insert(index, job);
+    drop(jobs);
+    touch(id);
+    id
+}
+
+fn remove_job(id: usize) -> Option<Job> {
+    JOB_ORDER.lock().unwrap().retain(|i| *i != id);
+    let mut jobs = JOBS.lock().unwrap();
+    let index = jobs.iter().position(|job| job.id == id)?;
+    Some(jobs.remove(index))
+}
+
+/* + for the current job, - for the previous one */
+fn marker(id: usize) -> char {
+    let order = JOB_ORDER.lock().unwrap();
+    let mut recent = order.iter().rev();
+    if recent.next() == Some(&id) {
+        '+'
+    } else if recent.next() == Some(&id) {
+        '-'
+    } else {
+        ' '
+    }
+}
+
+fn job_line(job: &Job, long: bool) -> String {
+    if long {
+        format!(
+            "[{}]{} {} {:<24}{}",
+            job.id,
+            marker(job.id),
+            job.pgid,
+            job.state(),
+            job.command_text()
+        )
+    } else {
+        format!(
+            "[{}]{}  {:<24}{}",
+            job.id,
+            marker(job.id),
+            job.state(),
+            job.command_text()
+        )
+    }
+}
+
+/* block until the foreground job exits or stops, or until the background job exits,
+ * return false if the wait for the background job is interrupted by Ctrl-C */
+fn wait_job(job: &mut Job, foreground: bool) -> bool {
+    let options = if foreground { libc::WUNTRACED } else { 0 };
+    for i in 0..job.procs.len() {
+        while job.procs[i].status.is_none() && job.procs[i].stopped.is_none() {
+            let mut wstatus = 0;
+            let pid = wait_child(job.procs[i].pid, &mut wstatus, options);
+            if pid < 0 {
+                if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                    if !foreground && signals::interrupted() {
+                        return false;
+                    }
+                    continue;
+                }
+                /* not a child anymore */
+                job.procs[i].status = Some(0);
+                break;
+            }
+            job.update(pid, wstatus);
+        }
+    }
+    true
+}
+
+fn set_terminal(pgid: i32, tmodes: Option<libc::termios>) {
+    let tty = TTY_FD.load(Ordering::SeqCst);
+    unsafe {
+        if let Some(tmodes) = tmodes {
+            libc::tcsetattr(tty, libc::TCSADRAIN, &tmodes);
+        }
+        libc::tcsetpgrp(tty, pgid);
+    }
+}
+
+/* wait for the job in the foreground, a stopped one goes to the table */
+pub fn run_foreground(mut job: Job) -> i32 {
+    if job_control() {
+        set_terminal(job.pgid, job.tmodes.take());
+    }
+    wait_job(&mut job, true);
+    set_pipe_status(job.statuses());
+    if job_control() {
+        if job.stopped() {
+            let mut tmodes: libc::termios = unsafe { std::mem::zeroed() };
+            if unsafe { libc::tcgetattr(TTY_FD.load(Ordering::SeqCst), &mut tmodes) } == 0 {
+                job.tmodes = Some(tmodes);
+            }
+        }
+        set_terminal(
+            SHELL_PGID.load(Ordering::SeqCst),
+            *SHELL_TMODES.lock().unwrap(),
+        );
+    }
+
+    if job.stopped() {
+        let signal = job
+            .procs
+            .iter()
+            .find_map(|proc| proc.stopped)
+            .unwrap_or(libc::SIGTSTP);
+        job.notified = true;
+        let id = add_job(job);
+        if let Some(job) = JOBS.lock().unwrap().iter().find(|job| job.id == id) {
+            eprintln!();
+            eprintln!("{}", job_line(job, false));
+        }
+        return 128 + signal;
+    }
+
+    /* the rest of the command line is skipped after Ctrl-C */
+    let interrupted = job.procs.iter().any(|proc| {
+        proc.status.is_some_and(|wstatus| {
+            libc::WIFSIGNALED(wstatus) && libc::WTERMSIG(wstatus) == libc::SIGINT
+        })
+    });
+    if interrupted && options::interactive() {
+        eprintln!();
+        signals::interrupt();
+    }
+
+    /* a crashed or killed cmd is reported, but not Ctrl-C or a closed pipe */
+    if let Some(wstatus) = job.procs[job.last_cmd].status {
+        if libc::WIFSIGNALED(wstatus)
+            && !matches!(libc::WTERMSIG(wstatus), libc::SIGINT | libc::SIGPIPE)
+        {
+            eprintln!("{}", signal_notice(wstatus));
+        }
+    }
+    job.status()
+}
+
+/* ${PIPESTATUS[@]} */
+pub fn set_pipe_status(statuses: Vec<i32>) {
+    vars::set_array(
+        "PIPESTATUS",
+        statuses.iter().map(|status| status.to_string()).collect(),
+    );
+}
+
+/* keep the job in the table, $! is the pid of its last process */
+pub fn run_background(job: Job) {
+    if let Some(proc) = job.procs.get(job.last_cmd) {
+        vars::set_last_bg_pid(proc.pid);
+    }
+    let pgid = job.pgid;
+    let id = add_job(job);
+    if options::interactive() {
+        eprintln!("[{}] {}", id, pgid);
+    }
+}
+
+/* collect the wait statuses of the jobs without blocking */
+pub fn update() {
+    loop {
+        let mut wstatus = 0;
+        let pid = wait_child(
+            -1,
+            &mut wstatus,
+            libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED,
+        );
+        if pid <= 0 {
+            break;
+        }
+        dispatch(pid, wstatus, &mut []);
+    }
+}
+
+/* give the wait status of a child reaped by a wait for any child to its job,
+ * or keep it for the wait by its pid */
+fn dispatch(pid: i32, wstatus: i32, jobs: &mut [Job]) {
+    if jobs.iter_mut().any(|job| job.update(pid, wstatus))
+        || JOBS
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .any(|job| job.update(pid, wstatus))
+    {
+        return;
+    }
+    if libc::WIFEXITED(wstatus) || libc::WIFSIGNALED(wstatus) {
+        REAPED.lock().unwrap().insert(pid, wstatus);
+    }
+}
+
+/* wait for a child without a job, like a tee */
+pub fn reap(pid: i32) {
+    let mut wstatus = 0;
+    while wait_child(pid, &mut wstatus, 0) < 0
+        && io::Error::last_os_error().kind() == io::ErrorKind::Interrupted
+    {}
+}
+
+/* report the jobs which have finished or stopped, before the prompt */
+pub fn notify() {
+    update();
+    if !options::interactive() {
+        return;
+    }
+    let jobs: Vec<Job> = JOBS.lock().unwrap().clone();
+    for job in jobs {
+        if job.notified || !(job.done() || job.stopped()) {
+            continue;
+        }
+        eprintln!("{}", job_line(&job, false));
+        if job.done() {
+            remove_job(job.id);
+        } else if let Some(job) = JOBS.lock().unwrap().iter_mut().find(|j| j.id == job.id) {
+            job.notified = true;
+        }
+    }
+}
+
+/* %n, %+ or %%, %-, %string (the command starts with it) and %?string (contains it) */
+pub fn find_job(spec: &str) -> Result<usize, JobFindFail> {
+    let spec = spec.strip_prefix('%').unwrap_or(spec);
+    let jobs = JOBS.lock().unwrap();
+    let order = JOB_ORDER.lock().unwrap();
+    let id = match spec {
+        "" | "+" | "%" => order.last().copied(),
+        "-" => order.iter().rev().nth(1).or(order.last()).copied(),
+        _ => {
+            if let Ok(id) = spec.parse::<usize>() {
+                jobs.iter().find(|job| job.id == id).map(|job| job.id)
+            } else {
+                let matched: Vec<usize> = match spec.strip_prefix('?') {
+                    Some(string) => jobs
+                        .iter()
+                        .filter(|job| job.command.contains(string))
+                        .map(|job| job.id)
+                        .collect(),
+                    None => jobs
+                        .iter()
+                        .filter(|job| job.command.starts_with(spec))
+                        .map(|job| job.id)
+                        .collect(),
+                };
+                if matched.len() > 1 {
+                    return Err(JobFindFail::Ambiguous);
+                }
+                matched.first().copied()
+            }
+        }
+    };
+    id.ok_or(JobFindFail::NoJob)
+}
+
+/* the job which has the pid */
+pub fn find_job_by_pid(pid: i32) -> Option<usize> {
+    JOBS.lock()
+        .unwrap()
+        .iter()
+        .find(|job| job.pids().contains(&pid))
+        .map(|job| job.id)
+}
+
+pub fn signal_pid(pid: i32, signal: i32) -> io::Result<()> {
+    if unsafe { libc::kill(pid, signal) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/* the stopped jobs are continued to receive TERM and HUP */
+pub fn signal_job(id: usize, signal: i32) -> io::Result<()> {
+    let Some(job) = get_job(id) else {
+        return Ok(());
+    };
+    let targets = if job_control() {
+        vec![-job.pgid]
+    } else {
+        job.pids()
+    };
+    for target in targets {
+        signal_pid(target, signal)?;
+        if job.stopped() && (signal == libc::SIGTERM || signal == libc::SIGHUP) {
+            signal_pid(target, libc::SIGCONT)?;
+        }
+    }
+    Ok(())
+}
+
+/* forward SIGHUP to the jobs, except the ones marked by disown -h */
+pub fn hangup() {
+    for job in JOBS.lock().unwrap().iter() {
+        if job.nohup || job.done() {
+            continue;
+        }
+        let targets = if job_control() {
+            vec![-job.pgid]
+        } else {
+            job.pids()
+        };
+        for target in targets {
+            let _ = signal_pid(target, libc::SIGHUP);
+            if job.stopped() {
+                let _ = signal_pid(target, libc::SIGCONT);
+            }
+        }
+    }
+}
+
+/* remove the job from the table, or keep it without SIGHUP */
+pub fn disown(id: usize, nohup: bool) {
+    if nohup {
+        if let Some(job) = JOBS.lock().unwrap().iter_mut().find(|job| job.id == id) {
+            job.nohup = true;
+        }
+    } else {
+        remove_job(id);
+    }
+}
+
+pub fn has_stopped_jobs() -> bool {
+    update();
+    JOBS.lock().unwrap().iter().any(|job| job.stopped())
+}
+
+pub fn job_ids() -> Vec<usize> {
+    JOBS.lock().unwrap().iter().map(|job| job.id).collect()
+}
+
+pub fn get_job(id: usize) -> Option<Job> {
+    JOBS.lock()
+        .unwrap()
+        .iter()
+        .find(|job| job.id == id)
+        .cloned()
+}
+
+/* print the job, the finished ones are removed after they are shown */
+pub fn print_job(id: usize, long: bool) -> io::Result<()> {
+    let Some(job) = get_job(id) else {
+        return Ok(());
+    };
+    writeln!(io::stdout(), "{}", job_line(&job, long))?;
+    if job.done() {
+        remove_job(id);
+    }
+    Ok(())
+}
+
+/* continue the job in the foreground */
+pub fn foreground(id: usize) -> i32 {
+    let Some(mut job) = remove_job(id) else {
+        return 1;
+    };
+    /* the job goes on even when the command can't be shown */
+    let _ = writeln!(io::stdout(), "{}", job.command);
+    job.resume();
+    run_foreground(job)
+}
+
+/* continue the stopped job in the background */
+pub fn background(id: usize) {
+    let mut jobs = JOBS.lock().unwrap();
+    let Some(job) = jobs.iter_mut().find(|job| job.id == id) else {
+        return;
+    };
+    job.resume();
+    let command = job.command.clone();
+    drop(jobs);
+    touch(id);
+    let _ = writeln!(io::stdout(), "[{}]{} {} &", id, marker(id), command);
+}
+
+/* block until the job exits, return its status and remove it, 130 if interrupted */
+pub fn wait_for(id: usize) -> i32 {
+    let Some(mut job) = get_job(id) else {
+        return 127;
+    };
+    if !wait_job(&mut job, false) {
+        if let Some(entry) = JOBS.lock().unwrap().iter_mut().find(|entry| entry.id == id) {
+            *entry = job;
+        }
+        return 130;
+    }
+    remove_job(id);
+    job.status()
+}
+
+/* block until one of the jobs kept out of the table exits, return its index, the jobs
+ * of the table reaped meanwhile are updated, None after Ctrl-C */
+pub fn wait_first(jobs: &mut [Job]) -> Option<usize> {
+    loop {
+        for job in jobs.iter_mut() {
+            job.take_reaped();
+        }
+        if let Some(index) = jobs.iter().position(Job::done) {
+            return Some(index);
+        }
+        let mut wstatus = 0;
+        let pid = wait_child(-1, &mut wstatus, 0);
+        if pid < 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted
+                && !signals::interrupted()
+            {
+                continue;
+            }
+            return None;
+        }
+        dispatch(pid, wstatus, jobs);
+    }
+}
+
+/* block until any of the running jobs exits, return its id */
+pub fn wait_any() -> Option<usize> {
+    loop {
+        update();
+        let jobs = JOBS.lock().unwrap();
+        if let Some(job) = jobs.iter().find(|job| job.done()) {
+            return Some(job.id);
+        }
+        if !jobs.iter().any(|job| !job.stopped()) {
+            return None;
+        }
+        drop(jobs);
+
+        let mut wstatus = 0;
+        let pid = wait_child(-1, &mut wstatus, 0);
+        if pid < 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted
+                && !signals::interrupted()
+            {
+                continue;
+            }
+            return None;
+        }
+        dispatch(pid, wstatus, &mut []);
+    }
+}
@@ -1,158 +1,370 @@
-use std::fs;
-use std::io::{self, Write};
-use std::path::Path;
-use std::process::Command;
-
-#[allow(dead_code)]
-const OPERATORS: [&str; 6] = [">", "1>", "2>", ">>", "1>>", "2>>"];
+use super::jobs;
+use super::options::{self, ShellOpt};
+use super::signals;
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
 
 #[derive(Debug)]
 pub enum Opt {
-    RedirectStdout(String),
-    RedirectStderr(String),
-    AppendStdout(String),
-    AppendStderr(String),
+    Redirect(i32, String),
+    Append(i32, String),
+    /* >| overwrites the file even with noclobber */
+    Clobber(i32, String),
+    Read(i32, String),
+    ReadWrite(i32, String),
+    /* n>&m and n<&m duplicate the fd m, n>&- closes n */
+    DupOutput(i32, String),
+    DupInput(i32, String),
+    /* {NAME}>&- and {NAME}<&- close the fd in $NAME, like {UP[1]}>&- for a coproc,
+     * it becomes a DupOutput once expanded */
+    CloseVar(String),
 }
 
-enum OptFind {
-    Stdout,
-    Stderr,
-}
+impl Opt {
+    pub fn file(&self) -> &str {
+        match self {
+            Opt::Redirect(_, file)
+            | Opt::Append(_, file)
+            | Opt::Clobber(_, file)
+            | Opt::Read(_, file)
+            | Opt::ReadWrite(_, file)
+            | Opt::DupOutput(_, file)
+            | Opt::DupInput(_, file) => file,
+            Opt::CloseVar(_) => "-",
+        }
+    }
+
+    /* the fd which is redirected */
+    pub fn fd(&self) -> i32 {
+        match self {
+            Opt::Redirect(fd, _)
+            | Opt::Append(fd, _)
+            | Opt::Clobber(fd, _)
+            | Opt::Read(fd, _)
+            | Opt::ReadWrite(fd, _)
+            | Opt::DupOutput(fd, _)
+            | Opt::DupInput(fd, _) => *fd,
+            Opt::CloseVar(_) => unreachable!("the fd of {{NAME}}>&- before the expansion"),
+        }
+    }
+
+    /* the output ones are written to all the targets with multios */
+    fn output(&self) -> bool {
+        matches!(
+            self,
+            Opt::Redirect(..) | Opt::Append(..) | Opt::Clobber(..) | Opt::DupOutput(..)
+        )
+    }
 
-pub enum OptParseFail {
-    NoOpt,
-    NofileName,
-    WrongOpt,
+    /* the fd is left out when it is the default one */
+    pub fn operator(&self) -> String {
+        if let Opt::CloseVar(name) = self {
+            return format!("{{{}}}>&", name);
+        }
+        let operator = match self {
+            Opt::Redirect(..) => ">",
+            Opt::Append(..) => ">>",
+            Opt::Clobber(..) => ">|",
+            Opt::Read(..) => "<",
+            Opt::ReadWrite(..) => "<>",
+            Opt::DupOutput(..) => ">&",
+            Opt::DupInput(..) | Opt::CloseVar(_) => "<&",
+        };
+        let default = if self.output() { 1 } else { 0 };
+        if self.fd() == default {
+            operator.to_string()
+        } else {
+            self.fd().to_string() + operator
+        }
+    }
+
+    pub fn with_file(&self, file: String) -> Opt {
+        match self {
+            Opt::Redirect(fd, _) => Opt::Redirect(*fd, file),
+            Opt::Append(fd, _) => Opt::Append(*fd, file),
+            Opt::Clobber(fd, _) => Opt::Clobber(*fd, file),
+            Opt::Read(fd, _) => Opt::Read(*fd, file),
+            Opt::ReadWrite(fd, _) => Opt::ReadWrite(*fd, file),
+            Opt::DupOutput(fd, _) => Opt::DupOutput(*fd, file),
+            Opt::DupInput(fd, _) => Opt::DupInput(*fd, file),
+            Opt::CloseVar(name) => Opt::CloseVar(name.clone()),
+        }
+    }
 }
 
-fn find_last_opt(opts: &[Opt], opt: OptFind) -> Option<&Opt> {
+/* >> appends to the file, > and >| truncate it, > can't overwrite a file with noclobber */
+fn open_file(opt: &Opt) -> io::Result<File> {
+    if let (Opt::Redirect(..), true) = (opt, options::enabled(ShellOpt::Noclobber)) {
+        return open_noclobber(opt.file());
+    }
+    let mut options = OpenOptions::new();
     match opt {
-        OptFind::Stdout => opts
-            .iter()
-            .filter(|opt| matches!(opt, Opt::RedirectStdout(_) | Opt::AppendStdout(_)))
-            .next_back(),
-        OptFind::Stderr => opts
-            .iter()
-            .filter(|opt| matches!(opt, Opt::RedirectStderr(_) | Opt::AppendStderr(_)))
-            .next_back(),
+        Opt::Append(..) => options.append(true).create(true),
+        Opt::Read(..) => options.read(true),
+        Opt::ReadWrite(..) => options.read(true).write(true).create(true),
+        _ => options.write(true).truncate(true).create(true),
+    };
+    options.open(opt.file())
+}
+
+/* the file is created with O_EXCL, an existing one is only opened when it isn't a
+ * regular file, like /dev/null, and it is never truncated */
+fn open_noclobber(path: &str) -> io::Result<File> {
+    match OpenOptions::new().write(true).create_new(true).open(path) {
+        Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+            let file = OpenOptions::new().write(true).open(path)?;
+            if file.metadata()?.is_file() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    "cannot overwrite existing file",
+                ));
+            }
+            Ok(file)
+        }
+        result => result,
+    }
+}
+
+/* the fds of the redirections are moved above the ones the user can name, so the
+ * targets are set without overwriting them */
+pub fn high_fd(fd: OwnedFd) -> io::Result<OwnedFd> {
+    let high = unsafe { libc::fcntl(fd.as_raw_fd(), libc::F_DUPFD_CLOEXEC, 10) };
+    if high < 0 {
+        return Err(io::Error::last_os_error());
     }
+    Ok(unsafe { OwnedFd::from_raw_fd(high) })
 }
 
-/* first -> stdout, second -> stderr */
-pub fn find_last_opts(opts: &[Opt]) -> (Option<&Opt>, Option<&Opt>) {
-    (
-        find_last_opt(opts, OptFind::Stdout),
-        find_last_opt(opts, OptFind::Stderr),
-    )
+fn bad_fd() -> io::Error {
+    io::Error::from_raw_os_error(libc::EBADF)
 }
 
-pub fn generate_opt(opt: &Opt) -> Command {
-    let mut command_generate: Command;
-    let command = "drshell".to_string();
-    let mut args = vec![];
+/* an fd and its stream, None closes the fd */
+pub type Stream = (i32, Option<OwnedFd>);
 
-    args.push("-o".to_string());
-    let file = match opt {
-        Opt::RedirectStdout(file) => {
-            args.push("redirect_stdout".to_string());
-            file
-        }
-        Opt::RedirectStderr(file) => {
-            args.push("redirect_stderr".to_string());
-            file
+/* the targets of the redirected fds of a cmd, more than one with multios,
+ * none for a closed fd */
+#[derive(Default)]
+pub struct Redirections {
+    fds: BTreeMap<i32, Vec<OwnedFd>>,
+}
+
+impl Redirections {
+    /* the pipes of a cmd are set before its redirections */
+    pub fn set(&mut self, fd: i32, target: OwnedFd) -> io::Result<()> {
+        self.fds.insert(fd, vec![high_fd(target)?]);
+        Ok(())
+    }
+
+    /* the fds are opened in order, without multios a later target replaces the earlier ones,
+     * which are still created and truncated */
+    pub fn open<'a>(&mut self, opts: &'a [Opt]) -> Result<(), (&'a Opt, io::Error)> {
+        for opt in opts {
+            self.open_opt(opt).map_err(|err| (opt, err))?;
         }
-        Opt::AppendStdout(file) => {
-            args.push("append_stdout".to_string());
-            file
+        Ok(())
+    }
+
+    fn open_opt(&mut self, opt: &Opt) -> io::Result<()> {
+        let targets = match opt {
+            Opt::DupOutput(_, fd) | Opt::DupInput(_, fd) if fd == "-" => vec![],
+            Opt::DupOutput(_, fd) | Opt::DupInput(_, fd) => {
+                let fd: i32 = fd.parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "ambiguous redirect")
+                })?;
+                match self.fds.get(&fd) {
+                    Some(targets) if targets.is_empty() => return Err(bad_fd()),
+                    Some(targets) => targets
+                        .iter()
+                        .map(|target| target.try_clone())
+                        .collect::<io::Result<Vec<OwnedFd>>>()?,
+                    /* an fd of the shell */
+                    None => {
+                        let copy = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 10) };
+                        if copy < 0 {
+                            return Err(bad_fd());
+                        }
+                        vec![unsafe { OwnedFd::from_raw_fd(copy) }]
+                    }
+                }
+            }
+            _ => vec![high_fd(OwnedFd::from(open_file(opt)?))?],
+        };
+        let multios = opt.output() && options::enabled(ShellOpt::Multios);
+        let fd_targets = self.fds.entry(opt.fd()).or_default();
+        if !multios || targets.is_empty() {
+            fd_targets.clear();
         }
-        Opt::AppendStderr(file) => {
-            args.push("append_stderr".to_string());
-            file
+        fd_targets.extend(targets);
+        Ok(())
+    }
+
+    /* the stream of each fd, None closes it, and the tees writing the streams
+     * with several targets */
+    pub fn streams(self) -> io::Result<(Vec<Stream>, Vec<Tee>)> {
+        let mut streams = vec![];
+        let mut tees = vec![];
+        for (fd, mut targets) in self.fds {
+            if targets.len() <= 1 {
+                streams.push((fd, targets.pop()));
+                continue;
+            }
+            let (reader, writer) = pipe()?;
+            streams.push((fd, Some(writer)));
+            tees.push(Tee { reader, targets });
         }
-    };
-    args.push(file.to_string());
+        Ok((streams, tees))
+    }
+}
 
-    command_generate = std::process::Command::new(command);
-    command_generate.args(args);
+pub fn open_fail_process(file: &str, err: &io::Error) -> i32 {
+    eprintln!("drshell: {}: {}", file, super::commands::error_reason(err));
+    1
+}
 
-    command_generate
+/* a forked shell which copies the reader to several targets */
+pub struct Tee {
+    reader: OwnedFd,
+    targets: Vec<OwnedFd>,
 }
 
-pub fn parse_opt(opt: Vec<String>) -> Result<Opt, OptParseFail> {
-    let optname = opt.first().ok_or(OptParseFail::NoOpt)?.as_str();
-    let filename = opt.get(1).ok_or(OptParseFail::NofileName)?.to_string();
+impl Tee {
+    /* the fds which the other processes close */
+    pub fn fds(&self) -> Vec<i32> {
+        let mut fds = vec![self.reader.as_raw_fd()];
+        fds.extend(self.targets.iter().map(AsRawFd::as_raw_fd));
+        fds
+    }
 
-    match optname {
-        "redirect_stdout" => Ok(Opt::RedirectStdout(filename)),
-        "redirect_stderr" => Ok(Opt::RedirectStdout(filename)),
-        "append_stdout" => Ok(Opt::AppendStdout(filename)),
-        "append_stderr" => Ok(Opt::AppendStderr(filename)),
-        &_ => Err(OptParseFail::WrongOpt),
+    /* in the process group pgid, the tee closes the others */
+    pub fn spawn(self, pgid: i32, others: &[i32]) -> io::Result<i32> {
+        let others = others.to_vec();
+        jobs::fork_shell(pgid, false, move || {
+            for fd in others {
+                unsafe { libc::close(fd) };
+            }
+            /* a closed target is dropped, the others are still written */
+            signals::ignore(libc::SIGPIPE);
+            let mut reader = File::from(self.reader);
+            let mut targets: Vec<File> = self.targets.into_iter().map(File::from).collect();
+            let mut buf = [0; 8192];
+            loop {
+                let len = match reader.read(&mut buf) {
+                    Ok(0) => return 0,
+                    Ok(len) => len,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(_) => return 1,
+                };
+                targets.retain_mut(|target| target.write_all(&buf[..len]).is_ok());
+            }
+        })
     }
 }
 
-pub fn operator_action(opt: Opt) {
-    let flag_redirect;
-    let file = match opt {
-        Opt::RedirectStdout(file) => {
-            flag_redirect = true;
-            file
-        }
-        Opt::RedirectStderr(file) => {
-            flag_redirect = true;
-            file
+/* in a child, set the fds to the streams or close them, it is async signal safe for pre_exec */
+pub fn dup_streams(streams: &[(i32, Option<i32>)]) {
+    for (fd, stream) in streams {
+        unsafe {
+            match stream {
+                Some(stream) => libc::dup2(*stream, *fd),
+                None => libc::close(*fd),
+            };
         }
-        Opt::AppendStdout(file) => {
-            flag_redirect = false;
-            file
-        }
-        Opt::AppendStderr(file) => {
-            flag_redirect = false;
-            file
+    }
+}
+
+/* both ends are closed on exec */
+pub fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(unsafe { (OwnedFd::from_raw_fd(fds[0]), OwnedFd::from_raw_fd(fds[1])) })
+}
+
+/* the shell's fds replaced for a redirected builtin, they are restored when dropped,
+ * then the tees are waited for */
+pub struct SavedFds {
+    saved: Vec<Stream>,
+    tees: Vec<i32>,
+}
+
+impl SavedFds {
+    pub fn new() -> Self {
+        Self {
+            saved: Vec::new(),
+            tees: Vec::new(),
         }
-    };
-    let path = Path::new(&file);
-    let mut line = String::new();
-    let stdin = io::stdin();
+    }
 
-    if flag_redirect || !path.exists() {
-        if let Err(err) = fs::write(path, "") {
-            eprintln!("{}", err);
+    pub fn redirect_all(&mut self, redirections: Redirections) -> io::Result<()> {
+        let (streams, mut tees) = redirections.streams()?;
+        let held: Vec<i32> = streams
+            .iter()
+            .filter_map(|(_, stream)| stream.as_ref().map(AsRawFd::as_raw_fd))
+            .collect();
+        while let Some(tee) = tees.pop() {
+            let mut others = held.clone();
+            others.extend(tees.iter().flat_map(Tee::fds));
+            self.tees.push(tee.spawn(jobs::shell_pgid(), &others)?);
         }
+        for (fd, stream) in streams {
+            self.redirect(fd, stream)?;
+        }
+        Ok(())
     }
-    loop {
-        match stdin.read_line(&mut line) {
-            Ok(num) => {
-                if num == 0 {
-                    return;
-                }
-            }
-            Err(_) => {
-                eprintln!("read stdin error!!!");
-                return;
+
+    /* the saved copy is above the fds the user can name, and closed on exec */
+    fn redirect(&mut self, target: i32, stream: Option<OwnedFd>) -> io::Result<()> {
+        flush_stdio();
+        let copy = unsafe { libc::fcntl(target, libc::F_DUPFD_CLOEXEC, 10) };
+        let saved = if copy < 0 {
+            let err = io::Error::last_os_error();
+            if err.raw_os_error() != Some(libc::EBADF) {
+                return Err(err);
             }
+            None
+        } else {
+            Some(unsafe { OwnedFd::from_raw_fd(copy) })
+        };
+        let result = match stream {
+            Some(stream) => unsafe { libc::dup2(stream.as_raw_fd(), target) },
+            None => unsafe { libc::close(target) },
+        };
+        if result < 0 && saved.is_some() {
+            return Err(io::Error::last_os_error());
         }
+        self.saved.push((target, saved));
+        Ok(())
+    }
 
-        fs::OpenOptions::new()
-            .append(true)
-            .open(path)
-            .expect("can't open the file!!!")
-            .write_all(line.as_bytes())
-            .expect("can't write to the file!!!");
-        line.clear();
+    /* exec keeps the redirections in the shell, and its tees running */
+    pub fn keep(mut self) {
+        flush_stdio();
+        self.saved.clear();
+        self.tees.clear();
     }
 }
 
-pub fn parse_opt_fail_process(reason: OptParseFail) {
-    match reason {
-        OptParseFail::NoOpt => {
-            eprintln!("no opt!!!");
-        }
-        OptParseFail::NofileName => {
-            eprintln!("no filename for opt!!!");
+impl Drop for SavedFds {
+    fn drop(&mut self) {
+        flush_stdio();
+        for (target, saved) in self.saved.drain(..).rev() {
+            match saved {
+                Some(fd) => unsafe { libc::dup2(fd.as_raw_fd(), target) },
+                None => unsafe { libc::close(target) },
+            };
         }
-        OptParseFail::WrongOpt => {
-            eprintln!("wrong opt!!!");
+        for pid in self.tees.drain(..) {
+            jobs::reap(pid);
         }
     }
 }
+
+/* the buffered output belongs to the fds it was written for */
+fn flush_stdio() {
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+}
@@ -0,0 +1,86 @@
+use lazy_static::lazy_static;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShellOpt {
+    Errexit,
+    Nounset,
+    Pipefail,
+    Noclobber,
+    Xtrace,
+    Verbose,
+    Ignoreeof,
+    Multios,
+}
+
+/* the option, its name for "set -o" and its letter for "set -" */
+const SHELL_OPTS: [(ShellOpt, &str, Option<char>); 8] = [
+    (ShellOpt::Errexit, "errexit", Some('e')),
+    (ShellOpt::Nounset, "nounset", Some('u')),
+    (ShellOpt::Pipefail, "pipefail", None),
+    (ShellOpt::Noclobber, "noclobber", Some('C')),
+    (ShellOpt::Xtrace, "xtrace", Some('x')),
+    (ShellOpt::Verbose, "verbose", Some('v')),
+    (ShellOpt::Ignoreeof, "ignoreeof", None),
+    (ShellOpt::Multios, "multios", None),
+];
+
+lazy_static! {
+    static ref ENABLED_OPTS: Mutex<Vec<ShellOpt>> = Mutex::new(vec![]);
+    static ref INTERACTIVE: Mutex<bool> = Mutex::new(false);
+}
+
+pub fn enabled(opt: ShellOpt) -> bool {
+    ENABLED_OPTS.lock().unwrap().contains(&opt)
+}
+
+pub fn set_opt(opt: ShellOpt, on: bool) {
+    let mut enabled_opts = ENABLED_OPTS.lock().unwrap();
+    enabled_opts.retain(|o| *o != opt);
+    if on {
+        enabled_opts.push(opt);
+    }
+}
+
+pub fn find_by_name(name: &str) -> Option<ShellOpt> {
+    SHELL_OPTS
+        .iter()
+        .find(|(_, n, _)| *n == name)
+        .map(|(opt, _, _)| *opt)
+}
+
+pub fn find_by_letter(letter: char) -> Option<ShellOpt> {
+    SHELL_OPTS
+        .iter()
+        .find(|(_, _, l)| *l == Some(letter))
+        .map(|(opt, _, _)| *opt)
+}
+
+/* the names and the states of all the options */
+pub fn list_opts() -> Vec<(&'static str, bool)> {
+    SHELL_OPTS
+        .iter()
+        .map(|(opt, name, _)| (*name, enabled(*opt)))
+        .collect()
+}
+
+pub fn set_interactive(interactive: bool) {
+    *INTERACTIVE.lock().unwrap() = interactive;
+}
+
+pub fn interactive() -> bool {
+    *INTERACTIVE.lock().unwrap()
+}
+
+/* the value of $- */
+pub fn flags() -> String {
+    let mut flags: String = SHELL_OPTS
+        .iter()
+        .filter(|(opt, _, _)| enabled(*opt))
+        .filter_map(|(_, _, letter)| *letter)
+        .collect();
+    if interactive() {
+        flags.push('i');
+    }
+    flags
+}
@@ -1,122 +1,215 @@
-use std::process::{Command, Stdio};
-
-pub struct Cmds {
-    cmds: Vec<Command>,
-    redirect_stdout: Option<Command>,
-    redirect_stderr: Option<Command>,
+use super::commands;
+use super::jobs::{self, Job};
+use super::operators::{self, Opt, Redirections, Stream, Tee};
+use std::io;
+use std::os::fd::{AsRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+/* a cmd of the pipeline, the builtins run in a fork of the shell */
+pub enum Stage {
+    Cmd(Command),
+    Shell(Box<dyn FnOnce() -> i32>),
 }
 
-impl Cmds {
-    pub fn new() -> Self {
-        Self {
-            cmds: Vec::new(),
-            redirect_stdout: None,
-            redirect_stderr: None,
+impl Stage {
+    pub fn new(cmd: commands::Cmd) -> Self {
+        match cmd {
+            commands::Cmd::NotBuiltin(words) => Self::spawned(words, vec![]),
+            commands::Cmd::WithVars(vars, cmd) if !cmd.is_builtin() => match *cmd {
+                commands::Cmd::NotBuiltin(words) => Self::spawned(words, vars),
+                _ => unreachable!("a builtin with variables runs in the shell"),
+            },
+            cmd => Stage::Shell(Box::new(move || commands::eval(cmd))),
         }
     }
 
-    pub fn add_cmd(&mut self, cmd: Command) {
-        self.cmds.push(cmd);
+    /* a cmd which can't be found fails in its stage, the variables are in its environment */
+    fn spawned(words: Vec<String>, vars: Vec<(String, String)>) -> Self {
+        match commands::generate_cmd(words) {
+            Ok(mut cmd) => {
+                cmd.envs(vars);
+                Stage::Cmd(cmd)
+            }
+            Err(reason) => Stage::Shell(Box::new(move || commands::parse_cmd_fail_process(reason))),
+        }
     }
+}
+
+/* the fds of a cmd set before its redirections, in place of the pipes */
+type Fds = Vec<(i32, OwnedFd)>;
+
+pub struct Cmds {
+    /* each cmd with its fds and its redirections */
+    cmds: Vec<(Stage, Fds, Vec<Opt>)>,
+}
 
-    pub fn add_redirect_stdout(&mut self, cmd: Command) {
-        self.redirect_stdout = Some(cmd);
+impl Cmds {
+    pub fn new() -> Self {
+        Self { cmds: Vec::new() }
     }
 
-    pub fn add_redirect_stderr(&mut self, cmd: Command) {
-        self.redirect_stderr = Some(cmd);
+    pub fn add_cmd(&mut self, cmd: Stage, opts: Vec<Opt>) {
+        self.cmds.push((cmd, vec![], opts));
     }
 
-    fn take_redirect_stdout(&mut self) -> Option<Command> {
-        self.redirect_stdout.take()
+    /* like the stdout of the cmd to a file */
+    pub fn add_cmd_with_fds(&mut self, cmd: Stage, fds: Fds) {
+        self.cmds.push((cmd, fds, vec![]));
     }
+}
 
-    fn take_redirect_stderr(&mut self) -> Option<Command> {
-        self.redirect_stderr.take()
+/* the fds of a stage, the others are inherited from the shell */
+struct StageIo {
+    streams: Vec<Stream>,
+    /* the pipe ends of the other stages and the tees, closed in the forked shells */
+    others: Vec<i32>,
+}
+
+impl StageIo {
+    fn raw_streams(&self) -> Vec<(i32, Option<i32>)> {
+        self.streams
+            .iter()
+            .map(|(fd, stream)| (*fd, stream.as_ref().map(AsRawFd::as_raw_fd)))
+            .collect()
     }
 
-    pub fn is_empty(&self) -> bool {
-        self.cmds.is_empty()
+    /* in the forked shell, a spawned cmd closes the others on exec */
+    fn apply(self) {
+        operators::dup_streams(&self.raw_streams());
+        for fd in self.others {
+            unsafe { libc::close(fd) };
+        }
     }
 }
 
 pub struct Pipeline {
-    childs: Vec<std::process::Child>,
+    pids: Vec<i32>,
+    /* the tees of the cmds' streams, not counted in the status */
+    tees: Vec<i32>,
+    /* the process group of the job, 0 until the first cmd is spawned */
+    pgid: i32,
+    foreground: bool,
 }
 
 impl Pipeline {
-    pub fn new() -> Self {
-        Self { childs: Vec::new() }
+    pub fn new(foreground: bool) -> Self {
+        Self {
+            pids: Vec::new(),
+            tees: Vec::new(),
+            pgid: 0,
+            foreground,
+        }
     }
 
-    pub fn pipe(&mut self, mut cmds: Cmds) -> std::io::Result<()> {
-        if cmds.is_empty() {
-            return Ok(());
+    /* the first cmd creates the process group of the job */
+    fn spawn(&mut self, stage: Stage, stage_io: StageIo) -> io::Result<()> {
+        let pid = match stage {
+            Stage::Cmd(mut cmd) => {
+                /* the streams stay open in the shell until the cmd is spawned */
+                let streams = stage_io.raw_streams();
+                unsafe {
+                    cmd.pre_exec(move || {
+                        operators::dup_streams(&streams);
+                        Ok(())
+                    });
+                }
+                jobs::set_process_group(&mut cmd, self.pgid, self.foreground);
+                match cmd.spawn() {
+                    Ok(child) => child.id() as i32,
+                    /* the exec fails, e.g. with a bad interpreter, the stage reports it
+                     * on its stderr and the other cmds still run */
+                    Err(err) => {
+                        let program = cmd.get_program().to_string_lossy().into_owned();
+                        jobs::fork_shell(self.pgid, self.foreground, move || {
+                            stage_io.apply();
+                            eprintln!("drshell: {}: {}", program, commands::error_reason(&err));
+                            match err.kind() {
+                                io::ErrorKind::NotFound => 127,
+                                _ => 126,
+                            }
+                        })?
+                    }
+                }
+            }
+            Stage::Shell(run) => jobs::fork_shell(self.pgid, self.foreground, move || {
+                stage_io.apply();
+                run()
+            })?,
+        };
+        jobs::join_process_group(pid, self.pgid);
+        if self.pgid == 0 {
+            self.pgid = pid;
         }
+        self.pids.push(pid);
+        Ok(())
+    }
 
-        /* check the stdout and stderr opts */
-        let opt_stdout = cmds.take_redirect_stdout();
-        let opt_stderr = cmds.take_redirect_stderr();
-
-        /* create the cmds' threads except the last one */
+    /* the pipes of each cmd are set before its redirections, which are opened in order */
+    pub fn pipe(&mut self, cmds: Cmds) -> io::Result<()> {
+        let count = cmds.cmds.len();
         let mut previous_stdout = None;
-        let mut last_cmd = cmds.cmds.pop().expect("cmds is not empty");
 
-        for mut cmd in cmds.cmds {
-            if let Some(stdout) = previous_stdout.take() {
-                cmd.stdin(stdout);
+        for (i, (mut cmd, fds, opts)) in cmds.cmds.into_iter().enumerate() {
+            let mut redirections = Redirections::default();
+            if let Some(reader) = previous_stdout.take() {
+                redirections.set(0, reader)?;
+            }
+            let mut next_stdin = None;
+            if i + 1 < count {
+                let (reader, writer) = operators::pipe()?;
+                redirections.set(1, writer)?;
+                next_stdin = Some(reader);
+            }
+            let next: Vec<i32> = next_stdin.iter().map(AsRawFd::as_raw_fd).collect();
+            for (fd, target) in fds {
+                redirections.set(fd, target)?;
             }
-            cmd.stdout(Stdio::piped());
-            let mut child = cmd.spawn()?;
-            let stdout = child.stdout.take().expect("never");
-            previous_stdout = Some(Stdio::from(stdout));
-            self.childs.push(child);
-        }
 
-        /* check whether to redirect the last cmd's stdio */
-        if let Some(stdout) = previous_stdout.take() {
-            last_cmd.stdin(stdout);
-        }
-        if opt_stdout.is_some() {
-            last_cmd.stdout(Stdio::piped());
-        }
-        if opt_stderr.is_some() {
-            last_cmd.stderr(Stdio::piped());
-        }
+            /* a cmd whose redirection fails only reports it, the other cmds still run */
+            if let Err((opt, err)) = redirections.open(&opts) {
+                let file = opt.file().to_string();
+                cmd = Stage::Shell(Box::new(move || operators::open_fail_process(&file, &err)));
+                redirections = Redirections::default();
+            }
 
-        /* create the last thread from the last cmd */
-        let mut child = last_cmd.spawn()?;
-        let child_stdout = child.stdout.take();
-        let child_stderr = child.stderr.take();
-        self.childs.push(child);
-
-        /* check whether to create the stdout thread */
-        if let Some(mut cmd) = opt_stdout {
-            cmd.stdin(Stdio::from(child_stdout.unwrap()));
-            let child = cmd.spawn()?;
-            self.childs.push(child);
-        }
+            let (streams, mut tees) = redirections.streams()?;
+            let mut others = next.clone();
+            others.extend(tees.iter().flat_map(Tee::fds));
+            self.spawn(cmd, StageIo { streams, others })?;
 
-        /* check whether to create the stderr thread */
-        if let Some(mut cmd) = opt_stderr {
-            cmd.stdin(Stdio::from(child_stderr.unwrap()));
-            let child = cmd.spawn()?;
-            self.childs.push(child);
+            while let Some(tee) = tees.pop() {
+                let mut others = next.clone();
+                others.extend(tees.iter().flat_map(Tee::fds));
+                self.tees.push(tee.spawn(self.pgid, &others)?);
+            }
+            previous_stdout = next_stdin;
         }
 
         Ok(())
     }
 
-    pub fn wait(&mut self) {
-        for child in &mut self.childs {
-            let _ = child.wait();
-        }
+    /* the last cmd's status is the pipeline's */
+    pub fn into_job(mut self, command: String) -> Job {
+        let last_cmd = self.pids.len() - 1;
+        self.pids.append(&mut self.tees);
+        Job::new(self.pgid, self.pids, last_cmd, command)
     }
 
-    pub fn kill(&mut self) -> std::io::Result<()> {
-        for child in &mut self.childs {
-            child.kill()?;
+    /* wait for the cmds, a stopped pipeline becomes a job */
+    pub fn wait(self, command: String) -> i32 {
+        jobs::run_foreground(self.into_job(command))
+    }
+
+    pub fn background(self, command: String) {
+        jobs::run_background(self.into_job(command));
+    }
+
+    /* kill and reap the cmds already spawned */
+    pub fn kill(&mut self) {
+        for pid in self.pids.drain(..).chain(self.tees.drain(..)) {
+            unsafe { libc::kill(pid, libc::SIGKILL) };
+            jobs::reap(pid);
         }
-        Ok(())
     }
 }
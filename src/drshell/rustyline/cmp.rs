@@ -11,6 +11,7 @@ fn sort_cmds() -> Vec<String> {
     for exe in drshell_env::find_exes() {
         cmds.push(exe.get_name());
     }
+    cmds.extend(commands::alias_names());
 
     cmds.sort();
     let mut unrepeat = vec![];
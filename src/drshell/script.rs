@@ -0,0 +1,475 @@
+use super::args::ShellOpts;
+use super::commands;
+use super::expand::{self, ExpandFail};
+use super::input::{self, CmdsAndOperators, Connector, InputSplitFail, ListItem};
+use super::jobs::{self, Job};
+use super::operators::{self, Opt, Redirections};
+use super::options::{self, ShellOpt};
+use super::pipline::{self, Pipeline, Stage};
+use super::signals;
+use super::times::Timer;
+use super::vars;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::mem::ManuallyDrop;
+use std::os::fd::FromRawFd;
+use std::path::Path;
+
+pub fn run_line(line: &str) -> i32 {
+    let items = match input::split_input(line.to_string()) {
+        Ok(items) => items,
+        Err(InputSplitFail::NoInput) => return vars::status(),
+        Err(reason) => {
+            let status = input::split_fail_process(reason);
+            vars::set_status(status);
+            return status;
+        }
+    };
+
+    /* the and-or lists, each one starts with a Seq connector */
+    let mut lists: Vec<Vec<ListItem>> = vec![];
+    for item in items {
+        match lists.last_mut() {
+            Some(list) if item.connector != Connector::Seq => list.push(item),
+            _ => lists.push(vec![item]),
+        }
+    }
+
+    let mut status = vars::status();
+    for list in lists {
+        if list.last().is_some_and(|item| item.background) {
+            status = run_background(list);
+            vars::set_status(status);
+        } else {
+            status = run_and_or(list, status);
+        }
+        if signals::interrupted() {
+            break;
+        }
+    }
+
+    status
+}
+
+/* the text of the pipeline for the job table */
+fn pipeline_text(pipeline: &CmdsAndOperators) -> String {
+    let mut text = match pipeline.time {
+        Some(true) => "time -p ".to_string(),
+        Some(false) => "time ".to_string(),
+        None => String::new(),
+    };
+    if pipeline.negated {
+        text += "! ";
+    }
+    let cmds: Vec<String> = pipeline
+        .cmds
+        .iter()
+        .zip(&pipeline.operators)
+        .map(|(cmd, opts)| {
+            let mut text = cmd.join(" ");
+            for opt in opts {
+                text += &format!(" {} {}", opt.operator(), opt.file());
+            }
+            text
+        })
+        .collect();
+    text += &cmds.join(" | ");
+    text
+}
+
+fn and_or_text(list: &[ListItem]) -> String {
+    let mut text = String::new();
+    for item in list {
+        match item.connector {
+            Connector::Seq => {}
+            Connector::And => text += " && ",
+            Connector::Or => text += " || ",
+        }
+        text += &pipeline_text(&item.pipeline);
+    }
+    text
+}
+
+/* a single pipeline is started directly, a longer and-or list or a timed pipeline
+ * runs in a forked shell */
+fn run_background(mut list: Vec<ListItem>) -> i32 {
+    let command = and_or_text(&list);
+    if list.len() == 1 && list[0].pipeline.time.is_none() {
+        let item = list.pop().expect("never");
+        return match parse_cmds_and_opts(item.pipeline) {
+            Ok((cmds, opts)) => execute_cmds_and_opts(cmds, opts, command, true),
+            Err(status) => status,
+        };
+    }
+
+    let status = vars::status();
+    match jobs::fork_shell(0, false, || run_and_or(list, status)) {
+        Ok(pid) => {
+            jobs::run_background(Job::new(pid, vec![pid], 0, command));
+            0
+        }
+        Err(err) => {
+            eprintln!("drshell: fork: {}", commands::error_reason(&err));
+            1
+        }
+    }
+}
+
+/* run the pipelines joined by && and ||, status is the one before the list */
+fn run_and_or(list: Vec<ListItem>, mut status: i32) -> i32 {
+    let mut items = list.into_iter().peekable();
+    while let Some(item) = items.next() {
+        let skip = match item.connector {
+            Connector::Seq => false,
+            Connector::And => status != 0,
+            Connector::Or => status == 0,
+        };
+        if skip {
+            continue;
+        }
+        let negated = item.pipeline.negated;
+        let timer = item.pipeline.time.map(|posix| (Timer::start(), posix));
+        let command = pipeline_text(&item.pipeline);
+        status = match parse_cmds_and_opts(item.pipeline) {
+            Ok((cmds, opts)) => execute_cmds_and_opts(cmds, opts, command, false),
+            Err(status) => single_status(status),
+        };
+        if negated {
+            status = (status == 0) as i32;
+        }
+        if let Some((timer, posix)) = timer {
+            timer.report(posix);
+        }
+        vars::set_status(status);
+        let interrupted = signals::interrupted();
+        commands::run_pending_traps();
+        if interrupted {
+            break;
+        }
+
+        /* errexit and ERR ignore the commands before && or || and the ones after ! */
+        let in_and_or = items
+            .peek()
+            .is_some_and(|next| next.connector != Connector::Seq);
+        if status != 0 && !negated && !in_and_or {
+            commands::run_err_trap();
+            if options::enabled(ShellOpt::Errexit) {
+                commands::exit_shell(status);
+            }
+        }
+    }
+
+    status
+}
+
+/* the lines ending with a backslash are joined with the next ones */
+fn join_lines(content: &str) -> Vec<String> {
+    let mut lines = vec![];
+    let mut joined = String::new();
+
+    for line in content.lines() {
+        match line.strip_suffix('\\') {
+            Some(line) => joined += line,
+            None => {
+                joined += line;
+                lines.push(std::mem::take(&mut joined));
+            }
+        }
+    }
+    if !joined.is_empty() {
+        lines.push(joined);
+    }
+
+    lines
+}
+
+pub fn run_file(path: &Path) -> io::Result<i32> {
+    let content = fs::read_to_string(path)?;
+    let mut status = 0;
+
+    for line in join_lines(&content) {
+        print_verbose(&line);
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        status = run_line(&line);
+        if signals::interrupted() {
+            break;
+        }
+    }
+
+    Ok(status)
+}
+
+/* the missing startup files are skipped */
+fn run_startup_file(path: &str) {
+    if let Err(err) = run_file(Path::new(path)) {
+        if err.kind() != io::ErrorKind::NotFound {
+            eprintln!("drshell: {}: {}", path, commands::error_reason(&err));
+        }
+    }
+}
+
+/* the profile for the login shells, then the rc file for the interactive ones */
+pub fn run_startup_files(opts: &ShellOpts) {
+    let home = env::var("HOME").ok();
+
+    if opts.login && !opts.noprofile {
+        if let Some(home) = &home {
+            run_startup_file(&(home.to_string() + "/.drshell_profile"));
+        }
+    }
+    if opts.interactive && !opts.norc {
+        let rcfile = match (&opts.rcfile, vars::get_var("ENV")) {
+            (Some(rcfile), _) => Some(rcfile.to_string()),
+            (None, Some(env)) => expand::expand_word_single(&env).ok(),
+            (None, None) => home.map(|home| home + "/.drshellrc"),
+        };
+        if let Some(rcfile) = rcfile {
+            run_startup_file(&rcfile);
+        }
+    }
+}
+
+/* read a line byte by byte, so the rest of stdin is left for the commands */
+fn read_stdin_line() -> io::Result<Option<String>> {
+    /* the fd 0 is not closed when the file is dropped */
+    let mut stdin = ManuallyDrop::new(unsafe { File::from_raw_fd(0) });
+    let mut line = vec![];
+    let mut byte = [0_u8; 1];
+
+    loop {
+        match stdin.read(&mut byte) {
+            Ok(0) => {
+                if line.is_empty() {
+                    return Ok(None);
+                }
+                break;
+            }
+            Ok(_) => {
+                if byte[0] == b'\n' {
+                    break;
+                }
+                line.push(byte[0]);
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+}
+
+/* the non-interactive shell runs the lines of stdin one by one */
+pub fn run_stdin() -> i32 {
+    let mut status = 0;
+    let mut joined = String::new();
+
+    loop {
+        let line = match read_stdin_line() {
+            Ok(Some(line)) => line,
+            Ok(None) => break,
+            Err(err) => {
+                eprintln!("drshell: {}", commands::error_reason(&err));
+                return 1;
+            }
+        };
+        print_verbose(&line);
+        if let Some(line) = line.strip_suffix('\\') {
+            joined += line;
+            continue;
+        }
+        joined += &line;
+        let line = std::mem::take(&mut joined);
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        status = run_line(&line);
+    }
+    if !joined.is_empty() {
+        status = run_line(&joined);
+    }
+
+    status
+}
+
+const DEFAULT_PS4: &str = "+ ";
+
+/* print the input lines as they are read, with verbose */
+pub fn print_verbose(line: &str) {
+    if options::enabled(ShellOpt::Verbose) {
+        eprintln!("{}", line);
+    }
+}
+
+/* print each cmd of the pipeline after $PS4, with xtrace */
+fn print_xtrace(cmds_words: &[Vec<String>], opts: &[Vec<Opt>]) {
+    let ps4 = vars::get_var("PS4").unwrap_or(DEFAULT_PS4.to_string());
+    let ps4 = expand::expand_word_single(&ps4).unwrap_or(ps4);
+
+    for (words, opts) in cmds_words.iter().zip(opts) {
+        let mut trace = words.clone();
+        for opt in opts {
+            trace.push(opt.operator().to_string() + " " + &expand::quote_if_needed(opt.file()));
+        }
+        eprintln!("{}{}", ps4, trace.join(" "));
+    }
+}
+
+/* the non-interactive shell exits when an expansion fails */
+fn expand_fail_process(reason: ExpandFail) -> i32 {
+    let status = expand::expand_fail_process(reason);
+    if !options::interactive() {
+        commands::exit_shell(status);
+    }
+    status
+}
+
+fn parse_cmds_and_opts(
+    cmds_and_operators: CmdsAndOperators,
+) -> Result<(Vec<commands::Cmd>, Vec<Vec<Opt>>), i32> {
+    let mut cmds = vec![];
+    let mut cmds_words = vec![];
+    for cmd in &cmds_and_operators.cmds {
+        let assignments: Vec<(&str, &str)> = cmd
+            .iter()
+            .map_while(|word| input::split_assignment(word))
+            .collect();
+        if assignments.len() == cmd.len() {
+            let mut expanded = vec![];
+            for (name, value) in assignments {
+                match expand::expand_word_single(value) {
+                    Ok(value) => expanded.push((name.to_string(), value)),
+                    Err(reason) => return Err(expand_fail_process(reason)),
+                }
+            }
+            cmds_words.push(
+                expanded
+                    .iter()
+                    .map(|(name, value)| name.to_string() + "=" + &expand::quote_if_needed(value))
+                    .collect(),
+            );
+            cmds.push(commands::Cmd::Assign(expanded));
+            continue;
+        }
+        /* the leading assignments are only for the cmd */
+        let mut prefix = vec![];
+        for (name, value) in &assignments {
+            match expand::expand_word_single(value) {
+                Ok(value) => prefix.push((name.to_string(), value)),
+                Err(reason) => return Err(expand_fail_process(reason)),
+            }
+        }
+        let words = match expand::expand_words(&cmd[assignments.len()..]) {
+            Ok(words) => words,
+            Err(reason) => return Err(expand_fail_process(reason)),
+        };
+        cmds_words.push(
+            prefix
+                .iter()
+                .map(|(name, value)| name.to_string() + "=" + &expand::quote_if_needed(value))
+                .chain(words.iter().map(|word| expand::quote_if_needed(word)))
+                .collect(),
+        );
+        match commands::parse_cmd(words) {
+            Ok(cmd) if prefix.is_empty() => cmds.push(cmd),
+            Ok(cmd) => cmds.push(commands::Cmd::WithVars(prefix, Box::new(cmd))),
+            Err(reason) => {
+                return Err(commands::parse_cmd_fail_process(reason));
+            }
+        }
+    }
+    let mut opts: Vec<Vec<Opt>> = vec![];
+    for cmd_opts in cmds_and_operators.operators {
+        let mut expanded = vec![];
+        for opt in cmd_opts {
+            if let Opt::CloseVar(var) = &opt {
+                let value = match expand::expand_word_single(&format!("${{{}}}", var)) {
+                    Ok(value) => value,
+                    Err(reason) => return Err(expand_fail_process(reason)),
+                };
+                match value.parse::<i32>() {
+                    Ok(fd) if fd >= 0 => expanded.push(Opt::DupOutput(fd, "-".to_string())),
+                    _ => {
+                        eprintln!("drshell: {{{}}}: {}: bad file descriptor", var, value);
+                        return Err(1);
+                    }
+                }
+                continue;
+            }
+            match expand::expand_word_single(opt.file()) {
+                Ok(file) => expanded.push(opt.with_file(file)),
+                Err(reason) => return Err(expand_fail_process(reason)),
+            }
+        }
+        opts.push(expanded);
+    }
+    if options::enabled(ShellOpt::Xtrace) {
+        print_xtrace(&cmds_words, &opts);
+    }
+
+    Ok((cmds, opts))
+}
+
+/* the status of a cmd run without a job is the only one in PIPESTATUS */
+fn single_status(status: i32) -> i32 {
+    jobs::set_pipe_status(vec![status]);
+    status
+}
+
+/* the builtins run in the shell, unless they are in a pipeline or in the background */
+fn execute_cmds_and_opts(
+    mut cmds: Vec<commands::Cmd>,
+    mut opts: Vec<Vec<Opt>>,
+    command: String,
+    background: bool,
+) -> i32 {
+    if !matches!(cmds.as_slice(), [commands::Cmd::Exit(_)]) {
+        commands::reset_stopped_jobs_warning();
+    }
+    /* a redirected builtin changes the shell's fds until it returns,
+     * exec keeps them */
+    let builtin = cmds.first().is_some_and(commands::Cmd::is_builtin);
+    if cmds.len() == 1 && builtin && !background {
+        let opts = opts.pop().expect("never");
+        let mut redirections = Redirections::default();
+        if let Err((opt, err)) = redirections.open(&opts) {
+            return single_status(operators::open_fail_process(opt.file(), &err));
+        }
+        let mut saved_fds = operators::SavedFds::new();
+        if let Err(err) = saved_fds.redirect_all(redirections) {
+            eprintln!("drshell: {}", commands::error_reason(&err));
+            return single_status(1);
+        }
+        let cmd = cmds.pop().expect("never");
+        let exec = matches!(cmd, commands::Cmd::Exec(_));
+        let status = commands::eval_in_shell(cmd);
+        if exec {
+            saved_fds.keep();
+        }
+        return single_status(status);
+    }
+
+    let mut cmds_generate = pipline::Cmds::new();
+    for (cmd, opts) in cmds.into_iter().zip(opts) {
+        cmds_generate.add_cmd(Stage::new(cmd), opts);
+    }
+
+    let mut pipeline = Pipeline::new(!background);
+    match pipeline.pipe(cmds_generate) {
+        Ok(()) if background => {
+            pipeline.background(command);
+            0
+        }
+        Ok(()) => pipeline.wait(command),
+        /* the shell can't fork, the cmds already spawned are killed */
+        Err(err) => {
+            pipeline.kill();
+            eprintln!("drshell: {}", commands::error_reason(&err));
+            single_status(1)
+        }
+    }
+}
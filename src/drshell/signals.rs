@@ -0,0 +1,170 @@
+use std::ffi::CStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+/* the names without "SIG" and the numbers */
+pub const SIGNALS: [(&str, i32); 30] = [
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ILL", libc::SIGILL),
+    ("TRAP", libc::SIGTRAP),
+    ("ABRT", libc::SIGABRT),
+    ("BUS", libc::SIGBUS),
+    ("FPE", libc::SIGFPE),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("URG", libc::SIGURG),
+    ("XCPU", libc::SIGXCPU),
+    ("XFSZ", libc::SIGXFSZ),
+    ("VTALRM", libc::SIGVTALRM),
+    ("PROF", libc::SIGPROF),
+    ("WINCH", libc::SIGWINCH),
+    ("IO", libc::SIGIO),
+    ("PWR", libc::SIGPWR),
+    ("SYS", libc::SIGSYS),
+];
+
+/* one bit for each caught signal, the handler only marks them */
+static PENDING_SIGNALS: AtomicU64 = AtomicU64::new(0);
+/* Ctrl-C stops the rest of the command line, until the next prompt */
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/* the SIGINT handler of the line editor, calling it makes the editor's read return
+ * with Interrupted once the read fails with EINTR */
+static EDITOR_HANDLER: AtomicUsize = AtomicUsize::new(0);
+static AT_PROMPT: AtomicBool = AtomicBool::new(false);
+/* a signal ended the line at the prompt */
+static EDITOR_WOKEN: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    PENDING_SIGNALS.fetch_or(1 << signal, Ordering::SeqCst);
+    wake_editor();
+}
+
+/* the signals are handled before the next prompt instead of after the next line */
+fn wake_editor() {
+    let handler = EDITOR_HANDLER.load(Ordering::SeqCst);
+    if handler == 0 || !AT_PROMPT.load(Ordering::SeqCst) {
+        return;
+    }
+    let handler = unsafe { std::mem::transmute::<usize, extern "C" fn(libc::c_int)>(handler) };
+    handler(libc::SIGINT);
+    EDITOR_WOKEN.store(true, Ordering::SeqCst);
+}
+
+extern "C" fn handle_interrupt(signal: libc::c_int) {
+    handle_signal(signal);
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/* INT, SIGINT, int or the number */
+pub fn find_signal(name: &str) -> Option<i32> {
+    if let Ok(num) = name.parse::<i32>() {
+        return SIGNALS.iter().find(|(_, n)| *n == num).map(|(_, n)| *n);
+    }
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+    SIGNALS.iter().find(|(n, _)| *n == name).map(|(_, n)| *n)
+}
+
+pub fn signal_name(signal: i32) -> Option<&'static str> {
+    SIGNALS.iter().find(|(_, n)| *n == signal).map(|(n, _)| *n)
+}
+
+/* Terminated, Killed, ... */
+pub fn signal_description(signal: i32) -> String {
+    let description = unsafe { libc::strsignal(signal) };
+    if description.is_null() {
+        return format!("Signal {}", signal);
+    }
+    unsafe { CStr::from_ptr(description) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn set_handler(signal: i32, handler: libc::sighandler_t, flags: libc::c_int) -> bool {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler;
+        action.sa_flags = flags;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(signal, &action, std::ptr::null_mut()) == 0
+    }
+}
+
+/* without SA_RESTART, so the read of the prompt is interrupted too */
+pub fn catch(signal: i32) -> bool {
+    let handler = handle_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    set_handler(signal, handler, 0)
+}
+
+/* the interactive shell survives Ctrl-C, and the blocking waits are interrupted */
+pub fn catch_interrupt() -> bool {
+    let handler = handle_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t;
+    set_handler(libc::SIGINT, handler, 0)
+}
+
+pub fn ignore(signal: i32) -> bool {
+    set_handler(signal, libc::SIG_IGN, libc::SA_RESTART)
+}
+
+pub fn set_default(signal: i32) -> bool {
+    set_handler(signal, libc::SIG_DFL, libc::SA_RESTART)
+}
+
+/* keep the SIGINT handler the editor installed, before it is replaced */
+pub fn keep_editor_handler() {
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    if unsafe { libc::sigaction(libc::SIGINT, std::ptr::null(), &mut action) } < 0 {
+        return;
+    }
+    let handler = action.sa_sigaction;
+    if handler != libc::SIG_DFL
+        && handler != libc::SIG_IGN
+        && action.sa_flags & libc::SA_SIGINFO == 0
+    {
+        EDITOR_HANDLER.store(handler, Ordering::SeqCst);
+    }
+}
+
+pub fn enter_prompt() {
+    EDITOR_WOKEN.store(false, Ordering::SeqCst);
+    AT_PROMPT.store(true, Ordering::SeqCst);
+}
+
+/* return whether a signal ended the line */
+pub fn leave_prompt() -> bool {
+    AT_PROMPT.store(false, Ordering::SeqCst);
+    EDITOR_WOKEN.swap(false, Ordering::SeqCst)
+}
+
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+pub fn clear_interrupted() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+/* act as if the shell got Ctrl-C, when the foreground job is killed by it */
+pub fn interrupt() {
+    handle_interrupt(libc::SIGINT);
+}
+
+/* the signals caught since the last call */
+pub fn take_pending() -> Vec<i32> {
+    let pending = PENDING_SIGNALS.swap(0, Ordering::SeqCst);
+    (1..64)
+        .filter(|signal| pending & (1 << signal) != 0)
+        .collect()
+}
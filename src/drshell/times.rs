@@ -0,0 +1,194 @@
+use super::vars;
+use lazy_static::lazy_static;
+use std::ops::{Add, Sub};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/* the format of bash, a newline then real, user and sys with 3 decimals */
+const DEFAULT_TIMEFORMAT: &str = "\nreal\t%3lR\nuser\t%3lU\nsys\t%3lS";
+const POSIX_TIMEFORMAT: &str = "real %2R\nuser %2U\nsys %2S";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CpuTimes {
+    pub user: Duration,
+    pub sys: Duration,
+}
+
+impl CpuTimes {
+    fn from_rusage(usage: &libc::rusage) -> Self {
+        let duration = |time: libc::timeval| {
+            Duration::from_secs(time.tv_sec as u64) + Duration::from_micros(time.tv_usec as u64)
+        };
+        Self {
+            user: duration(usage.ru_utime),
+            sys: duration(usage.ru_stime),
+        }
+    }
+}
+
+impl Add for CpuTimes {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            user: self.user + other.user,
+            sys: self.sys + other.sys,
+        }
+    }
+}
+
+impl Sub for CpuTimes {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self {
+            user: self.user.saturating_sub(other.user),
+            sys: self.sys.saturating_sub(other.sys),
+        }
+    }
+}
+
+lazy_static! {
+    /* the times of the children reaped by the shell, from wait4 */
+    static ref CHILDREN_TIMES: Mutex<CpuTimes> = Mutex::new(CpuTimes::default());
+}
+
+/* a child was reaped, its times include the children it waited for */
+pub fn add_child(usage: &libc::rusage) {
+    let mut times = CHILDREN_TIMES.lock().unwrap();
+    *times = *times + CpuTimes::from_rusage(usage);
+}
+
+pub fn children_times() -> CpuTimes {
+    *CHILDREN_TIMES.lock().unwrap()
+}
+
+/* the times of the shell itself, with its builtins */
+pub fn shell_times() -> CpuTimes {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) };
+    CpuTimes::from_rusage(&usage)
+}
+
+/* the start of a timed pipeline */
+pub struct Timer {
+    real: Instant,
+    cpu: CpuTimes,
+}
+
+impl Timer {
+    pub fn start() -> Self {
+        Self {
+            real: Instant::now(),
+            cpu: shell_times() + children_times(),
+        }
+    }
+
+    /* print the times spent since the start with $TIMEFORMAT, or the posix format */
+    pub fn report(self, posix: bool) {
+        let real = self.real.elapsed();
+        let cpu = shell_times() + children_times() - self.cpu;
+        let format = if posix {
+            POSIX_TIMEFORMAT.to_string()
+        } else {
+            vars::get_var("TIMEFORMAT").unwrap_or(DEFAULT_TIMEFORMAT.to_string())
+        };
+        /* an empty TIMEFORMAT prints nothing */
+        if format.is_empty() {
+            return;
+        }
+        eprintln!("{}", format_times(&format, real, cpu));
+    }
+}
+
+/* 1.500 or 0m1.500s with l */
+fn format_duration(duration: Duration, precision: usize, long: bool) -> String {
+    let secs = duration.as_secs_f64();
+    if long {
+        let minutes = (secs / 60.0).floor();
+        format!("{}m{:.*}s", minutes, precision, secs - minutes * 60.0)
+    } else {
+        format!("{:.*}", precision, secs)
+    }
+}
+
+/* %[p][l]R, %[p][l]U and %[p][l]S are the real, user and sys times, with p decimals
+ * from 0 to 3, %P is the cpu percentage and %% is a % */
+pub fn format_times(format: &str, real: Duration, cpu: CpuTimes) -> String {
+    let mut text = String::new();
+    let mut chars = format.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            text.push(c);
+            continue;
+        }
+        let mut precision = 3;
+        if let Some(digit) = chars.next_if(char::is_ascii_digit) {
+            precision = digit.to_digit(10).expect("never").min(3) as usize;
+        }
+        let long = chars.next_if_eq(&'l').is_some();
+        match chars.next() {
+            Some('R') => text += &format_duration(real, precision, long),
+            Some('U') => text += &format_duration(cpu.user, precision, long),
+            Some('S') => text += &format_duration(cpu.sys, precision, long),
+            Some('P') => {
+                let percent = if real.is_zero() {
+                    0.0
+                } else {
+                    (cpu.user + cpu.sys).as_secs_f64() * 100.0 / real.as_secs_f64()
+                };
+                text += &format!("{:.2}", percent);
+            }
+            Some('%') => text.push('%'),
+            /* an unknown sequence is kept */
+            Some(c) => {
+                text.push('%');
+                text.push(c);
+            }
+            None => text.push('%'),
+        }
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn times(format: &str) -> String {
+        let cpu = CpuTimes {
+            user: Duration::from_millis(1250),
+            sys: Duration::from_millis(500),
+        };
+        format_times(format, Duration::from_millis(61500), cpu)
+    }
+
+    #[test]
+    fn default_formats() {
+        assert_eq!(
+            times(DEFAULT_TIMEFORMAT),
+            "\nreal\t1m1.500s\nuser\t0m1.250s\nsys\t0m0.500s"
+        );
+        assert_eq!(times(POSIX_TIMEFORMAT), "real 61.50\nuser 1.25\nsys 0.50");
+    }
+
+    #[test]
+    fn precision_and_long_times() {
+        assert_eq!(times("%R %0U %1S"), "61.500 1 0.5");
+        assert_eq!(times("%1lR %lU"), "1m1.5s 0m1.250s");
+        /* at most 3 decimals */
+        assert_eq!(times("%9S"), "0.500");
+    }
+
+    #[test]
+    fn percent_and_unknown_sequences() {
+        assert_eq!(times("%P%%"), "2.85%");
+        assert_eq!(times("%q %"), "%q %");
+        assert_eq!(
+            format_times("%P", Duration::ZERO, CpuTimes::default()),
+            "0.00"
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use super::options;
+use lazy_static::lazy_static;
+use std::collections::HashMap;
+use std::env;
+use std::process;
+use std::sync::Mutex;
+
+const DEFAULT_PROMPT: &str = "$ ";
+
+lazy_static! {
+    /* $0 is the first one */
+    static ref POSITIONAL_PARAMS: Mutex<Vec<String>> = Mutex::new(vec!["drshell".to_string()]);
+    static ref LAST_STATUS: Mutex<i32> = Mutex::new(0);
+    static ref LAST_BG_PID: Mutex<Option<i32>> = Mutex::new(None);
+    /* $$ is the pid of the shell, also in the forked ones */
+    static ref SHELL_PID: u32 = process::id();
+    /* the exported variables are kept in the environment of the process */
+    static ref SHELL_VARS: Mutex<HashMap<String, String>> = Mutex::new(HashMap::new());
+    /* the arrays are never exported, $NAME is ${NAME[0]} */
+    static ref SHELL_ARRAYS: Mutex<HashMap<String, Vec<String>>> = Mutex::new(HashMap::new());
+}
+
+pub fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => {
+            chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        _ => false,
+    }
+}
+
+pub fn set_var(name: &str, value: &str) {
+    if let Some(array) = SHELL_ARRAYS.lock().unwrap().get_mut(name) {
+        match array.first_mut() {
+            Some(first) => *first = value.to_string(),
+            None => array.push(value.to_string()),
+        }
+        return;
+    }
+    if env::var_os(name).is_some() {
+        env::set_var(name, value);
+    } else {
+        SHELL_VARS
+            .lock()
+            .unwrap()
+            .insert(name.to_string(), value.to_string());
+    }
+}
+
+pub fn export_var(name: &str, value: Option<&str>) {
+    let old = SHELL_VARS.lock().unwrap().remove(name);
+    match value.map(String::from).or(old) {
+        Some(value) => env::set_var(name, value),
+        None => {
+            if env::var_os(name).is_none() {
+                env::set_var(name, "");
+            }
+        }
+    }
+}
+
+/* put back the value of a variable set for one cmd, None unsets it */
+pub fn restore_var(name: &str, value: Option<String>) {
+    match value {
+        Some(value) => set_var(name, &value),
+        None => {
+            SHELL_VARS.lock().unwrap().remove(name);
+            env::remove_var(name);
+        }
+    }
+}
+
+/* sorted by the names */
+pub fn exported_vars() -> Vec<(String, String)> {
+    let mut exported: Vec<(String, String)> = env::vars_os()
+        .map(|(name, value)| {
+            (
+                name.to_string_lossy().into_owned(),
+                value.to_string_lossy().into_owned(),
+            )
+        })
+        .collect();
+    exported.sort();
+    exported
+}
+
+/* the array replaces a variable with the same name */
+pub fn set_array(name: &str, values: Vec<String>) {
+    SHELL_VARS.lock().unwrap().remove(name);
+    env::remove_var(name);
+    SHELL_ARRAYS
+        .lock()
+        .unwrap()
+        .insert(name.to_string(), values);
+}
+
+pub fn get_array(name: &str) -> Option<Vec<String>> {
+    SHELL_ARRAYS.lock().unwrap().get(name).cloned()
+}
+
+/* the shell variables and the environment, sorted by the names */
+pub fn all_vars() -> Vec<(String, String)> {
+    let mut all = exported_vars();
+    for (name, value) in SHELL_VARS.lock().unwrap().iter() {
+        all.push((name.clone(), value.clone()));
+    }
+    all.sort();
+    all
+}
+
+pub fn prompt() -> String {
+    get_var("PS1").unwrap_or(DEFAULT_PROMPT.to_string())
+}
+
+pub fn set_script_name(name: String) {
+    POSITIONAL_PARAMS.lock().unwrap()[0] = name;
+}
+
+/* return the old params, without $0 */
+pub fn set_positional_params(params: Vec<String>) -> Vec<String> {
+    let mut positional_params = POSITIONAL_PARAMS.lock().unwrap();
+    let old = positional_params.split_off(1);
+    positional_params.extend(params);
+    old
+}
+
+/* without $0 */
+pub fn positional_params() -> Vec<String> {
+    POSITIONAL_PARAMS.lock().unwrap()[1..].to_vec()
+}
+
+pub fn set_status(status: i32) {
+    *LAST_STATUS.lock().unwrap() = status;
+}
+
+pub fn status() -> i32 {
+    *LAST_STATUS.lock().unwrap()
+}
+
+pub fn shell_pid() -> u32 {
+    *SHELL_PID
+}
+
+/* $! */
+pub fn set_last_bg_pid(pid: i32) {
+    *LAST_BG_PID.lock().unwrap() = Some(pid);
+}
+
+pub fn get_var(name: &str) -> Option<String> {
+    match name {
+        "?" => Some(status().to_string()),
+        "$" => Some(shell_pid().to_string()),
+        "!" => LAST_BG_PID.lock().unwrap().map(|pid| pid.to_string()),
+        "-" => Some(options::flags()),
+        "#" => Some(positional_params().len().to_string()),
+        "@" | "*" => Some(positional_params().join(" ")),
+        _ => {
+            if let Ok(n) = name.parse::<usize>() {
+                POSITIONAL_PARAMS.lock().unwrap().get(n).cloned()
+            } else if let Some(value) = SHELL_VARS.lock().unwrap().get(name) {
+                Some(value.clone())
+            } else if let Some(array) = SHELL_ARRAYS.lock().unwrap().get(name) {
+                array.first().cloned()
+            } else {
+                env::var(name).ok()
+            }
+        }
+    }
+}
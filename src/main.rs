@@ -2,26 +2,43 @@ mod drshell;
 
 use crate::drshell::args::{self, ArgsParseSuccess};
 use crate::drshell::commands::{self, Api};
-use crate::drshell::input::{self, CmdsAndOperators};
-use crate::drshell::operators;
-use crate::drshell::pipline::{self, Pipeline};
+use crate::drshell::jobs;
+use crate::drshell::options::{self, ShellOpt};
 use crate::drshell::rustyline::Rustyline;
+use crate::drshell::script;
+use crate::drshell::signals;
+use crate::drshell::vars;
 use rustyline::error::ReadlineError;
 use rustyline::{Config, Editor, Event, EventHandler, KeyCode, KeyEvent, Modifiers};
 use std::env;
+use std::process;
 
 fn main() -> rustyline::Result<()> {
-    /* read history */
-    if let Ok(home) = env::var("HOME") {
-        commands::api(Api::ReadHistoryFromFile((home + "/.drhistory").as_str()));
-    }
-
     /* args */
     let args = env::args();
-    if let Ok(ArgsParseSuccess::SubCmdExeOver) = args::parse_args(args) {
-        return Ok(());
+    let opts = match args::parse_args(args) {
+        Ok(ArgsParseSuccess::SubCmdExeOver(status)) => {
+            commands::run_exit_trap();
+            process::exit(status);
+        }
+        Ok(ArgsParseSuccess::ReadCmds(opts)) => opts,
+        Err(never) => match never {},
+    };
+    options::set_interactive(opts.interactive);
+    /* read history, only an interactive shell has one */
+    if opts.interactive {
+        if let Ok(home) = env::var("HOME") {
+            commands::api(Api::ReadHistoryFromFile((home + "/.drhistory").as_str()));
+        }
+    }
+    script::run_startup_files(&opts);
+    if !opts.interactive {
+        let status = script::run_stdin();
+        commands::run_exit_trap();
+        process::exit(status);
     }
 
+    jobs::init();
     println!(">::< welcome to drshell");
 
     /* rustyline */
@@ -31,6 +48,11 @@ fn main() -> rustyline::Result<()> {
         .auto_add_history(false)
         .build();
     let mut rl = Editor::with_config(config)?;
+    /* after the editor, which has its own SIGINT handler */
+    signals::keep_editor_handler();
+    signals::catch_interrupt();
+    signals::catch(libc::SIGHUP);
+    signals::catch(libc::SIGTERM);
     rl.set_helper(Some(Rustyline::new()));
     rl.bind_sequence(
         Event::KeySeq(vec![KeyEvent(KeyCode::Up, Modifiers::NONE)]),
@@ -41,27 +63,43 @@ fn main() -> rustyline::Result<()> {
         EventHandler::Conditional(Box::new(Rustyline)),
     );
 
-    'repl: loop {
-        match rl.readline("$ ") {
+    /* the Ctrl-D ignored in a row with ignoreeof */
+    let mut ignored_eofs = 0;
+    loop {
+        jobs::notify();
+        commands::run_pending_traps();
+        signals::clear_interrupted();
+        signals::enter_prompt();
+        let readline = rl.readline(&vars::prompt());
+        /* a signal caught at the prompt ends the line, kill -INT is like Ctrl-C */
+        let woken = signals::leave_prompt()
+            && matches!(readline, Err(ReadlineError::Interrupted))
+            && !signals::interrupted();
+        /* a hangup exits before the closed terminal is used */
+        commands::run_pending_traps();
+        if !matches!(readline, Err(ReadlineError::Eof)) {
+            ignored_eofs = 0;
+        }
+        match readline {
+            _ if woken => {}
             Ok(line) => {
                 let line = handle_history_with_line(line);
-                match input::split_input(line) {
-                    Ok(cmds_and_operators) => {
-                        let Some((cmds, opts)) = parse_cmds_and_opts(cmds_and_operators) else {
-                            continue 'repl;
-                        };
-                        execute_cmds_and_opts(cmds, opts);
-                    }
-                    Err(reason) => {
-                        input::split_fail_process(reason);
-                    }
-                }
+                script::print_verbose(&line);
+                script::run_line(&line);
             }
             Err(ReadlineError::Interrupted) => {
+                /* Ctrl-C clears the line */
                 println!("^C");
-                break;
+                vars::set_status(130);
             }
             Err(ReadlineError::Eof) => {
+                /* a closed terminal can't loop forever, the shell exits after
+                 * $IGNOREEOF of them, 10 by default like bash */
+                if options::enabled(ShellOpt::Ignoreeof) && ignored_eofs < ignoreeof_limit() {
+                    ignored_eofs += 1;
+                    eprintln!("Use \"exit\" to leave the shell.");
+                    continue;
+                }
                 println!("^D");
                 break;
             }
@@ -72,6 +110,9 @@ fn main() -> rustyline::Result<()> {
         }
     }
 
+    commands::run_exit_trap();
+    jobs::release_terminal();
+
     /* write history */
     if let Ok(home) = env::var("HOME") {
         commands::api(Api::WriteHistoryToFile((home + "/.drhistory").as_str()));
@@ -80,6 +121,12 @@ fn main() -> rustyline::Result<()> {
     Ok(())
 }
 
+fn ignoreeof_limit() -> u32 {
+    vars::get_var("IGNOREEOF")
+        .and_then(|limit| limit.parse().ok())
+        .unwrap_or(10)
+}
+
 fn handle_history_with_line(mut line: String) -> String {
     if let Some(input) = commands::api(commands::Api::InputHistory) {
         line = input;
@@ -89,48 +136,3 @@ fn handle_history_with_line(mut line: String) -> String {
     }
     line
 }
-
-fn parse_cmds_and_opts(
-    cmds_and_operators: CmdsAndOperators,
-) -> Option<(Vec<commands::Cmd>, Vec<operators::Opt>)> {
-    let mut cmds = vec![];
-    for cmd in &cmds_and_operators.cmds {
-        match commands::parse_cmd(cmd.clone()) {
-            Ok(cmd) => cmds.push(cmd),
-            Err(reason) => {
-                commands::parse_cmd_fail_process(reason);
-                return None;
-            }
-        }
-    }
-    let opts: Vec<operators::Opt> = cmds_and_operators.operators.into_iter().collect();
-
-    Some((cmds, opts))
-}
-
-fn execute_cmds_and_opts(mut cmds: Vec<commands::Cmd>, opts: Vec<operators::Opt>) {
-    if cmds.len() == 1 && opts.is_empty() {
-        commands::eval(cmds.pop().expect("never"));
-        return;
-    }
-
-    let mut cmds_generate = pipline::Cmds::new();
-    for cmd in cmds {
-        let cmd = commands::generate_cmd(cmd);
-        cmds_generate.add_cmd(cmd);
-    }
-    let (redirect_stdout, redirect_stderr) = operators::find_last_opts(&opts);
-    if let Some(opt) = redirect_stdout {
-        cmds_generate.add_redirect_stdout(operators::generate_opt(opt));
-    }
-    if let Some(opt) = redirect_stderr {
-        cmds_generate.add_redirect_stderr(operators::generate_opt(opt));
-    }
-
-    let mut pipeline = Pipeline::new();
-    if pipeline.pipe(cmds_generate).is_ok() {
-        pipeline.wait();
-    } else if let Err(err) = pipeline.kill() {
-        eprintln!("{}", err);
-    }
-}